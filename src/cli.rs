@@ -1,51 +1,162 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{io::IsTerminal, path::PathBuf, str::FromStr};
 
+use chrono::{NaiveDate, NaiveDateTime};
 use clap::{Subcommand, ValueEnum};
 use itertools::Itertools;
 use lang_packer_model::{generic_utils::{PackingError, SyntaxTree}, pack_trees::{HasRule, TokenPacker}};
 use pest::Parser;
 use thiserror::Error;
 
-use crate::parser::{Rule, Time, TimeRangeEnd, TimesheetsParser};
+use crate::{evaluator::Window, parser::{Date, Period, Rule, Time, TimeRangeEnd, TimesheetsParser}};
 
 #[derive(clap::Parser, Debug)]
 #[command(version, about)]
 pub struct TshArgs {
     pub file_path: PathBuf,
 
+    /// Config file to use in place of the per-user default
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Expected length of each working day from Monday to Friday, e.g. "7h 30m"
+    #[arg(long, global = true, value_parser = parse_period)]
+    pub working_day: Option<Period>,
+
+    /// Length of lunch assumed when none has been logged, e.g. "30m"
+    #[arg(long, global = true, value_parser = parse_period)]
+    pub lunch: Option<Period>,
+
+    /// Evaluate as though it were this local time, e.g. "2024-09-24 12:00"
+    #[arg(long, global = true, value_parser = parse_at)]
+    pub at: Option<NaiveDateTime>,
+
+    /// Show this many of the most recent weeks [default: 4]
+    #[arg(long, global = true, conflicts_with_all = ["all", "from", "to", "month"])]
+    pub weeks: Option<usize>,
+
+    /// Show every week
+    #[arg(long, global = true, conflicts_with_all = ["from", "to", "month"])]
+    pub all: bool,
+
+    /// Show the weeks from the one containing this date, written as for WEEK
+    #[arg(long, global = true, value_parser = parse_date, conflicts_with = "month")]
+    pub from: Option<NaiveDate>,
+
+    /// Show the weeks up to the one containing this date, written as for WEEK
+    #[arg(long, global = true, value_parser = parse_date, conflicts_with = "month")]
+    pub to: Option<NaiveDate>,
+
+    /// Show the weeks with days in this month, e.g. "2024-09"
+    #[arg(long, global = true, value_parser = parse_month)]
+    pub month: Option<NaiveDate>,
+
+    /// How to print the balances and the days shown
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// Draw boxes from plain ASCII, for terminals without box-drawing characters
+    #[arg(long, global = true)]
+    pub ascii: bool,
+
+    /// Whether to colour credits, deficits and warnings
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub color: ColorChoice,
+
     #[command(subcommand)]
     pub command: Option<Action>,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum ColorChoice {
+    /// Only when printing to a terminal, and NO_COLOR isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(&self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => std::io::stdout().is_terminal()
+                && std::env::var_os("NO_COLOR").is_none_or(|no_color| no_color.is_empty()),
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum OutputFormat {
+    /// Boxes for reading in a terminal
+    #[default]
+    Human,
+    /// Versioned JSON, as described in the README
+    Json,
+    /// One row per day, or per log for `show`, for spreadsheets
+    Csv,
+    /// The same rows as CSV, as a Markdown table
+    Markdown,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Action {
-    /// Mark start of log event
+    /// Mark start of log event, as a range ending at NOW under today
     Start {
         #[arg(value_enum)] 
         log_type: LogType,
 
+        /// Defaults to the current time
         #[arg(value_parser = parse_time)]
-        time_range: Time,
+        time_range: Option<Time>,
+
+        /// What the log was for, required for WORK and BREAK
+        #[arg(long)]
+        commentary: Option<String>,
     },
 
-    /// Mark end of log event
+    /// Mark end of log event, replacing the NOW it was started with
     End {
         #[arg(value_enum)]
         log_type: LogType,
 
+        /// Defaults to the current time, e.g. "17:30" or "01:15+1"
         #[arg(value_parser = parse_time_range_end)]
-        time_range: TimeRangeEnd,
+        time_range: Option<TimeRangeEnd>,
+    },
+
+    /// Rewrite the timesheet in canonical layout
+    Fmt {
+        /// Only report whether the timesheet is already formatted, failing if it isn't
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Look for mistakes in the timesheet, failing if any are errors
+    Check,
+
+    /// Show every log of a day, with its commentary
+    Show {
+        /// Written as for WEEK, e.g. "2024-09-16" or "16th September 2024"
+        #[arg(value_parser = parse_date)]
+        date: NaiveDate,
     },
+
+    /// Show the leave taken, booked and remaining in each year with an ALLOWANCE
+    Leave,
 }
 
 #[derive(Error, Debug)]
 pub enum RuleParseError {
     #[error("{0}")]
-    Parsing(#[from] ::pest::error::Error<Rule>),
+    Parsing(#[from] Box<::pest::error::Error<Rule>>),
 
     #[error("{0}")]
     ExactlyOne(String),
 
+    #[error("Unexpected '{rest}' after '{parsed}'")]
+    Trailing { parsed: String, rest: String },
+
     #[error("{0}")]
     Packing(#[from] PackingError<Rule>),
 }
@@ -55,14 +166,19 @@ fn parse_struct<S>(s: &str, rule: Rule) -> Result<S, RuleParseError>
 where
     S: TokenPacker + HasRule<Rule = Rule>,
 {
-    let pairs = TimesheetsParser::parse(rule, s)?;
-
-    let tree = pairs.into_iter()
-        .map(SyntaxTree::from)
+    let pair = TimesheetsParser::parse(rule, s)
+        .map_err(Box::new)?
         .exactly_one()
         .map_err(|err| RuleParseError::ExactlyOne(err.to_string()))?;
-    
-    S::pack(&tree)
+
+    // The rule only has to match the start of the input, so anything after it is turned away here
+    let end = pair.as_span().end();
+
+    if end < s.len() {
+        return Err(RuleParseError::Trailing { parsed: s[..end].trim_end().to_owned(), rest: s[end..].trim_start().to_owned() });
+    }
+
+    S::pack(&SyntaxTree::from(pair))
         .map_err(RuleParseError::Packing)
 }
 
@@ -92,6 +208,52 @@ fn parse_time_range_end(s: &str) -> Result<TimeRangeEnd, String> {
         .map_err(|e| format!("{e}"))
 }
 
+impl FromStr for Period {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_struct(s, Rule::PERIOD)
+    }
+}
+
+/// Only hours and minutes, as days are measured by the periods given here
+fn parse_period(s: &str) -> Result<Period, String> {
+    let period = s.parse::<Period>()
+        .map_err(|e| format!("{e}"))?;
+
+    match period.fixed() {
+        Some(_) => Ok(period),
+        None => Err(format!("'{s}' must be in hours and minutes, e.g. \"7h 30m\"")),
+    }
+}
+
+impl FromStr for Date {
+    type Err = RuleParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_struct(s, Rule::DATE)
+    }
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    s.parse::<Date>()
+        .map_err(|e| format!("{e}"))?
+        .to_naive_date()
+        .ok_or_else(|| format!("'{s}' is not a valid date"))
+}
+
+/// The first of the month
+fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{s}-01"), "%Y-%m-%d")
+        .map_err(|_| format!("'{s}' is not a month such as \"2024-09\""))
+}
+
+fn parse_at(s: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| format!("'{s}' is not a time such as \"2024-09-24 12:00\""))
+}
+
 #[derive(ValueEnum, Debug, Clone)]
 pub enum LogType {
     WorkingDay,
@@ -101,6 +263,47 @@ pub enum LogType {
     Lunch,
 }
 
+impl TshArgs {
+    /// Fails in the same way as clap when the range given runs backwards
+    pub fn window(&self) -> Result<Window, clap::Error> {
+        match self {
+            TshArgs { weeks: Some(weeks), .. } => Ok(Window::Last(*weeks)),
+            TshArgs { all: true, .. } => Ok(Window::All),
+            TshArgs { month: Some(month), .. } => Ok(Window::month(*month)),
+            TshArgs { from: None, to: None, .. } => Ok(Window::default()),
+            TshArgs { from: Some(from), to: Some(to), .. } if from > to => {
+                let message = format!("--from {from} is after --to {to}");
+                Err(<TshArgs as clap::CommandFactory>::command().error(clap::error::ErrorKind::ArgumentConflict, message))
+            },
+            TshArgs { from, to, .. } => Ok(Window::Between { from: *from, to: *to }),
+        }
+    }
+}
+
 pub fn parse_cli() -> TshArgs {
     <TshArgs as clap::Parser>::parse()
 }
+
+#[test]
+fn check_parsing_covers_the_whole_input() {
+    assert!("7h 30m".parse::<Period>().is_ok());
+    assert!("16th September 2024".parse::<Date>().is_ok());
+
+    assert_eq!("7h 30".parse::<Period>().unwrap_err().to_string(), "Unexpected '30' after '7h'");
+    assert!("6h garbage".parse::<Period>().is_err());
+    assert!("2024-12-25 | Christmas Day".parse::<Date>().is_err());
+    assert!("09:00 - 17:00".parse::<Time>().is_err());
+}
+
+#[test]
+fn check_backwards_ranges_are_rejected() {
+    let window = |args: &[&str]| <TshArgs as clap::Parser>::parse_from(["timesheets", "timesheet.tsh"].iter().chain(args)).window();
+
+    assert!(window(&["--from", "2024-09-01", "--to", "2024-10-01"]).is_ok());
+    assert!(window(&["--from", "2024-10-01", "--to", "2024-10-01"]).is_ok());
+
+    let err = window(&["--from", "2024-10-01", "--to", "2024-09-01"]).unwrap_err();
+
+    assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    assert!(err.to_string().contains("--from 2024-10-01 is after --to 2024-09-01"));
+}
@@ -0,0 +1,388 @@
+use std::{env, fs::read_to_string, io, path::{Path, PathBuf}};
+
+use chrono::{Datelike, NaiveDate, TimeDelta, Weekday};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{cli::{RuleParseError, TshArgs}, holidays::{load_holidays, Holiday, HolidaysError}, parser::{Date, Period}};
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Time expected to be worked on each day of the week, indexed from Monday
+#[derive(Debug, Clone)]
+pub struct Schedule(pub [TimeDelta; 7]);
+
+impl Schedule {
+    pub fn expected(&self, weekday: Weekday) -> TimeDelta {
+        self.0[weekday.num_days_from_monday() as usize]
+    }
+
+    pub(crate) fn set(&mut self, weekday: Weekday, expected: TimeDelta) {
+        self.0[weekday.num_days_from_monday() as usize] = expected;
+    }
+
+    /// Sets the same expectation for Monday to Friday, leaving the weekend alone
+    pub(crate) fn set_working_week(&mut self, expected: TimeDelta) {
+        self.0[..5].fill(expected);
+    }
+}
+
+impl Schedule {
+    /// Nothing expected on any day, for a contract to fill in
+    pub(crate) fn empty() -> Self {
+        Schedule([TimeDelta::zero(); 7])
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        let mut schedule = Schedule([TimeDelta::zero(); 7]);
+        schedule.set_working_week(TimeDelta::hours(8));
+
+        schedule
+    }
+}
+
+/// Which day is credited with time worked past midnight
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overnight {
+    /// All of it goes to the day the range started on
+    #[default]
+    StartDay,
+    /// Time after midnight goes to the following day
+    Split,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Followed until the first contract starts
+    pub schedule: Schedule,
+    /// Each replacing the schedule from its date onwards, in date order
+    pub contracts: Vec<(NaiveDate, Schedule)>,
+    pub lunch: TimeDelta,
+    pub week_start: Weekday,
+    pub overnight: Overnight,
+    /// Days with nothing expected of them, whatever the schedule says
+    pub holidays: Vec<Holiday>,
+}
+
+impl Config {
+    pub fn holiday(&self, date: NaiveDate) -> Option<&Holiday> {
+        self.holidays.iter()
+            .find(|holiday| holiday.date == date)
+    }
+
+    /// The schedule of the latest contract to have started by the given date
+    pub fn schedule_on(&self, date: NaiveDate) -> &Schedule {
+        self.contracts.iter()
+            .rev()
+            .find(|(from, _)| *from <= date)
+            .map_or(&self.schedule, |(_, schedule)| schedule)
+    }
+
+    /// Time expected to be worked on the given date, which is nothing on a holiday
+    pub fn expected(&self, date: NaiveDate) -> TimeDelta {
+        match self.holiday(date) {
+            Some(_) => TimeDelta::zero(),
+            None => self.schedule_on(date).expected(date.weekday()),
+        }
+    }
+
+    /// Replaces any contract starting on the same date
+    pub(crate) fn set_contract(&mut self, from: NaiveDate, schedule: Schedule) {
+        self.contracts.retain(|(other, _)| *other != from);
+        self.contracts.push((from, schedule));
+        self.contracts.sort_by_key(|(from, _)| *from);
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            schedule: Schedule::default(),
+            contracts: vec![],
+            lunch: TimeDelta::minutes(30),
+            week_start: Weekday::Mon,
+            overnight: Overnight::default(),
+            holidays: vec![],
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Unable to read config file {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("Invalid config file {}: {source}", path.display())]
+    Toml { path: PathBuf, source: Box<toml::de::Error> },
+
+    #[error("Invalid period '{value}' for '{key}' in {}: {source}", path.display())]
+    Period { path: PathBuf, key: &'static str, value: String, source: RuleParseError },
+
+    #[error("Invalid period '{value}' for '{key}' in {}, which must be in hours and minutes, e.g. '7h 30m'", path.display())]
+    DayPeriod { path: PathBuf, key: &'static str, value: String },
+
+    #[error("Invalid day '{value}' for '{key}' in {}, expected a day such as 'Monday'", path.display())]
+    Weekday { path: PathBuf, key: &'static str, value: String },
+
+    #[error("Invalid date '{value}' for '{key}' in {}, expected a date such as '2024-06-03'", path.display())]
+    Date { path: PathBuf, key: &'static str, value: String },
+
+    #[error("{0}")]
+    Holidays(HolidaysError),
+}
+
+/// One layer of configuration, as found in a single TOML file.
+/// Periods are written using timesheet syntax, e.g. `working_day = "7h 30m"`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    working_day: Option<String>,
+    lunch: Option<String>,
+    week_start: Option<String>,
+    overnight: Option<Overnight>,
+    /// Relative to the config file
+    holidays: Option<PathBuf>,
+    #[serde(default)]
+    schedule: ScheduleFile,
+    #[serde(default)]
+    contract: Vec<ContractFile>,
+}
+
+/// A schedule from the given date, laid out as at the top level, e.g. `working_day = "6h"` and `[contract.schedule]`.
+/// Days left out of it have nothing expected of them
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ContractFile {
+    from: String,
+    working_day: Option<String>,
+    #[serde(default)]
+    schedule: ScheduleFile,
+}
+
+/// Per-weekday overrides of `working_day`, e.g. `friday = "4h"`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    monday: Option<String>,
+    tuesday: Option<String>,
+    wednesday: Option<String>,
+    thursday: Option<String>,
+    friday: Option<String>,
+    saturday: Option<String>,
+    sunday: Option<String>,
+}
+
+impl ScheduleFile {
+    fn into_entries(self) -> [(Weekday, &'static str, Option<String>); 7] {
+        [
+            (Weekday::Mon, "schedule.monday", self.monday),
+            (Weekday::Tue, "schedule.tuesday", self.tuesday),
+            (Weekday::Wed, "schedule.wednesday", self.wednesday),
+            (Weekday::Thu, "schedule.thursday", self.thursday),
+            (Weekday::Fri, "schedule.friday", self.friday),
+            (Weekday::Sat, "schedule.saturday", self.saturday),
+            (Weekday::Sun, "schedule.sunday", self.sunday),
+        ]
+    }
+}
+
+impl ConfigFile {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.to_owned(), source })?;
+
+        toml::from_str(&text)
+            .map_err(|source| ConfigError::Toml { path: path.to_owned(), source: Box::new(source) })
+    }
+
+    fn apply(self, config: &mut Config, path: &Path) -> Result<(), ConfigError> {
+        let parse_period = |key: &'static str, value: String| {
+            let period = value.parse::<Period>()
+                .map_err(|source| ConfigError::Period { path: path.to_owned(), key, value: value.clone(), source })?;
+
+            period.fixed()
+                .ok_or(ConfigError::DayPeriod { path: path.to_owned(), key, value })
+        };
+
+        let apply_schedule = |schedule: &mut Schedule, working_day: Option<String>, entries: ScheduleFile| {
+            if let Some(working_day) = working_day {
+                schedule.set_working_week(parse_period("working_day", working_day)?);
+            }
+
+            for (weekday, key, expected) in entries.into_entries() {
+                if let Some(expected) = expected {
+                    schedule.set(weekday, parse_period(key, expected)?);
+                }
+            }
+
+            Ok(())
+        };
+
+        apply_schedule(&mut config.schedule, self.working_day, self.schedule)?;
+
+        for ContractFile { from, working_day, schedule: entries } in self.contract {
+            let date = from.parse::<Date>()
+                .ok()
+                .and_then(|date| date.to_naive_date())
+                .ok_or_else(|| ConfigError::Date { path: path.to_owned(), key: "contract.from", value: from })?;
+
+            let mut schedule = Schedule::empty();
+            apply_schedule(&mut schedule, working_day, entries)?;
+
+            config.set_contract(date, schedule);
+        }
+
+        if let Some(lunch) = self.lunch {
+            config.lunch = parse_period("lunch", lunch)?;
+        }
+
+        if let Some(week_start) = self.week_start {
+            config.week_start = week_start.parse::<Weekday>()
+                .map_err(|_| ConfigError::Weekday { path: path.to_owned(), key: "week_start", value: week_start })?;
+        }
+
+        if let Some(overnight) = self.overnight {
+            config.overnight = overnight;
+        }
+
+        if let Some(holidays) = self.holidays {
+            let holidays = path.parent()
+                .unwrap_or(Path::new(""))
+                .join(holidays);
+
+            config.holidays = load_holidays(&holidays)
+                .map_err(ConfigError::Holidays)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/timesheets/config.toml`, falling back to `~/.config/timesheets/config.toml`
+fn user_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("timesheets").join(CONFIG_FILE_NAME))
+}
+
+/// `my-timesheet.tsh` is configured by `my-timesheet.toml`, if present
+fn timesheet_config_path(timesheet_path: &Path) -> PathBuf {
+    timesheet_path.with_extension("toml")
+}
+
+impl Config {
+    /// Layers, in increasing order of precedence:
+    ///  - built-in defaults
+    ///  - the per-user config file (or the file given by `--config`)
+    ///  - the config file sitting alongside the timesheet
+    ///  - command line flags
+    pub fn load(args: &TshArgs) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+
+        // An explicitly requested config file must exist, the implicit ones are optional
+        match &args.config {
+            Some(path) => ConfigFile::read(path)?.apply(&mut config, path)?,
+            None => {
+                if let Some(path) = user_config_path().filter(|path| path.is_file()) {
+                    ConfigFile::read(&path)?.apply(&mut config, &path)?;
+                }
+            }
+        }
+
+        let path = timesheet_config_path(&args.file_path);
+
+        if path.is_file() {
+            ConfigFile::read(&path)?.apply(&mut config, &path)?;
+        }
+
+        // Periods in days are turned away when parsing the command line
+        if let Some(working_day) = args.working_day.as_ref().and_then(Period::fixed) {
+            config.schedule.set_working_week(working_day);
+        }
+
+        if let Some(lunch) = args.lunch.as_ref().and_then(Period::fixed) {
+            config.lunch = lunch;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Writes the files into a directory of their own, then loads the config for `main.tsh` in it with the given flags
+#[cfg(test)]
+fn load_files(name: &str, files: &[(&str, &str)], flags: &[&str]) -> Config {
+    let dir = env::temp_dir().join(format!("timesheets-config-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (path, text) in files {
+        std::fs::write(dir.join(path), text).unwrap();
+    }
+
+    let mut args: Vec<std::ffi::OsString> = vec!["timesheets".into(), dir.join("main.tsh").into(), "--config".into(), dir.join("user.toml").into()];
+    args.extend(flags.iter().map(Into::into));
+
+    let config = Config::load(&<TshArgs as clap::Parser>::parse_from(args));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    config.unwrap()
+}
+
+#[test]
+fn check_config_is_layered_from_defaults_to_flags() {
+    let config = load_files("defaults", &[("user.toml", "")], &[]);
+
+    assert_eq!(config.schedule.expected(Weekday::Mon), TimeDelta::hours(8));
+    assert_eq!(config.schedule.expected(Weekday::Sat), TimeDelta::zero());
+    assert_eq!(config.lunch, TimeDelta::minutes(30));
+    assert_eq!(config.week_start, Weekday::Mon);
+    assert!(matches!(config.overnight, Overnight::StartDay));
+
+    let files = [
+        ("user.toml", "working_day = \"7h\"\nlunch = \"1h\"\nweek_start = \"Sunday\"\novernight = \"split\"\n"),
+        ("main.toml", "lunch = \"45m\"\n\n[schedule]\nfriday = \"4h\"\n"),
+    ];
+
+    let config = load_files("layers", &files, &[]);
+
+    assert_eq!(config.schedule.expected(Weekday::Mon), TimeDelta::hours(7));
+    assert_eq!(config.schedule.expected(Weekday::Fri), TimeDelta::hours(4));
+    assert_eq!(config.lunch, TimeDelta::minutes(45));
+    assert_eq!(config.week_start, Weekday::Sun);
+    assert!(matches!(config.overnight, Overnight::Split));
+
+    let config = load_files("flags", &files, &["--working-day", "6h", "--lunch", "20m"]);
+
+    assert_eq!(config.schedule.expected(Weekday::Mon), TimeDelta::hours(6));
+    assert_eq!(config.schedule.expected(Weekday::Fri), TimeDelta::hours(6));
+    assert_eq!(config.lunch, TimeDelta::minutes(20));
+    assert_eq!(config.week_start, Weekday::Sun);
+}
+
+#[test]
+fn check_schedule_days_override_the_working_day() {
+    let config = load_files("schedule", &[("user.toml", "working_day = \"7h\"\n\n[schedule]\nfriday = \"4h\"\nsaturday = \"2h\"\n")], &[]);
+
+    assert_eq!(config.schedule.0, [7, 7, 7, 7, 4, 2, 0].map(TimeDelta::hours));
+}
+
+#[test]
+fn check_timesheet_contracts_replace_config_contracts_from_the_same_date() {
+    use crate::evaluator::{evaluate_str, EvalContext, TotalDelta};
+
+    let config = load_files("contracts", &[("user.toml", "[[contract]]\nfrom = \"2024-09-16\"\nworking_day = \"7h\"\n\n[[contract]]\nfrom = \"2024-09-23\"\nworking_day = \"4h\"\n")], &[]);
+
+    let code = "CONTRACT FROM 2024-09-23\n  Monday 6h\n\nWEEK 2024-09-16\n  Monday\n    WORKING DAY 09:00 - 16:00\n\nWEEK 2024-09-23\n  Monday\n    WORKING DAY 09:00 - 15:00\n\n  Tuesday\n    WORKING DAY 09:00 - 10:00\n";
+    let TotalDelta { week_deltas, .. } = evaluate_str(code, &EvalContext { config, ..EvalContext::at_utc("2024-09-27 18:00") });
+
+    let expected: Vec<_> = week_deltas.iter()
+        .flat_map(|week| &week.day_deltas)
+        .map(|day| day.expected.num_hours())
+        .collect();
+
+    assert_eq!(expected, [7, 6, 0, 0]);
+}
@@ -0,0 +1,232 @@
+/*
+ * Edits are made by splicing text in at the byte offsets of the parsed spans,
+ * so that everything else in the file (spacing, commentary, ordering) is left exactly as it was
+ */
+
+use chrono::{Datelike, NaiveDate, TimeDelta};
+use thiserror::Error;
+
+use crate::{cli::LogType, evaluator::EvalContext, parser::*};
+
+#[derive(Error, Debug)]
+pub enum EditError {
+    #[error("{0}")]
+    Parsing(ParsingError),
+
+    #[error("'{log}' on line {line} is still open, so it must be ended before another is started")]
+    AlreadyOpen { log: String, line: usize },
+
+    #[error("There is no {0} ending at NOW to end")]
+    NothingOpen(&'static str),
+
+    #[error("A log can't be ended at NOW, as that would leave it open")]
+    EndAtNow,
+
+    #[error("'{log}' on line {line} was started before yesterday, so the time it ended must be given")]
+    EndTimeNeeded { log: String, line: usize },
+
+    #[error("{0} must say what it was for, e.g. with --commentary \"Dentist\"")]
+    MissingSummary(&'static str),
+}
+
+/// The edited timesheet, along with the line that was added or changed
+#[derive(Debug)]
+pub struct Edited {
+    pub code: String,
+    pub line: String,
+    pub date: NaiveDate,
+}
+
+impl LogType {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            LogType::WorkingDay => "WORKING DAY",
+            LogType::Work => "WORK",
+            LogType::Break => "BREAK",
+            LogType::Leave => "LEAVE",
+            LogType::Lunch => "LUNCH",
+        }
+    }
+
+    fn matches(&self, event: &LogEvent) -> bool {
+        matches!(
+            (self, event),
+            (LogType::WorkingDay, LogEvent::WorkingDay(_))
+            | (LogType::Work, LogEvent::Work(_))
+            | (LogType::Break, LogEvent::Break(_))
+            | (LogType::Leave, LogEvent::Leave(_))
+            | (LogType::Lunch, LogEvent::Lunch(_))
+        )
+    }
+}
+
+/// A log of the given type ending at NOW, along with the date it was logged on
+struct OpenLog<'a> {
+    log: &'a Log,
+    spans: &'a LogSpans,
+    date: Option<NaiveDate>,
+}
+
+/// Every log of the given type left open, in the order they appear in the file
+fn open_logs<'a>(timesheets: &'a Timesheets, log_type: &LogType, ctx: &EvalContext) -> Vec<OpenLog<'a>> {
+    timesheets.weeks.iter()
+        .flat_map(|Spanned { node: week @ Week(.., Days(days)), spans: week_spans }| {
+            let starting_date = week.starting_date(ctx.config.week_start, week_spans).ok();
+
+            days.iter()
+                .zip(&week_spans.days)
+                .flat_map(move |(day @ Day(_, logs), day_spans)| {
+                    let date = starting_date.map(|starting_date| day.date(starting_date));
+
+                    logs.iter()
+                        .zip(&day_spans.logs)
+                        .map(move |(log, spans)| OpenLog { log, spans, date })
+                })
+        })
+        .filter(|OpenLog { log: Log(event), .. }| log_type.matches(event) && is_open(event))
+        .collect()
+}
+
+fn is_open(event: &LogEvent) -> bool {
+    matches!(event.time_period(), Some(TimePeriod::TimeRange(TimeRange(_, TimeRangeEnd::Now(_)))))
+}
+
+fn splice(code: &str, at: usize, until: usize, text: &str) -> String {
+    [&code[..at], text, &code[until..]].concat()
+}
+
+fn line_ending(code: &str) -> &'static str {
+    match code.contains("\r\n") {
+        true => "\r\n",
+        false => "\n",
+    }
+}
+
+/// Adds `<LOG TYPE> <time> - NOW` under today, creating its WEEK and day if they aren't there yet.
+/// New weeks and days are placed in date order amongst the existing ones
+pub fn start_log(code: &str, log_type: &LogType, time: Time, commentary: Option<&str>, ctx: &EvalContext) -> Result<Edited, EditError> {
+    let keyword = log_type.keyword();
+
+    let line = match commentary.map(str::trim) {
+        Some(commentary) if !commentary.is_empty() => format!("{keyword} {time} - NOW | {commentary}"),
+        _ if matches!(log_type, LogType::Work | LogType::Break) => return Err(EditError::MissingSummary(keyword)),
+        _ => format!("{keyword} {time} - NOW"),
+    };
+
+    let today = ctx.today();
+    let week_start = ctx.config.week_start;
+    let offset = (7 + today.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    let starting_date = today - TimeDelta::days(offset.into());
+
+    let nl = line_ending(code);
+    let log_text = format!("    {line}{nl}");
+    let day_text = format!("  {}{nl}{log_text}", DayName(today.weekday()));
+    let week_text = format!("WEEK {}{nl}{day_text}", LongDate::from(starting_date));
+
+    let edited = |code: String| Ok(Edited { code, line: line.clone(), date: today });
+
+    // There's nothing to find a place amongst, so the week simply follows whatever blank lines there are
+    if code.trim().is_empty() {
+        return edited(format!("{code}{week_text}"));
+    }
+
+    let timesheets = parse_timesheets(code)
+        .map_err(EditError::Parsing)?;
+
+    if let Some(OpenLog { log, spans, .. }) = open_logs(&timesheets, log_type, ctx).pop() {
+        return Err(EditError::AlreadyOpen { log: log.to_string(), line: spans.span.line });
+    }
+
+    let dated_weeks: Vec<_> = timesheets.weeks.iter()
+        .map(|Spanned { node: week, spans: week_spans }| (week.starting_date(week_start, week_spans).ok(), week, week_spans))
+        .collect();
+
+    let Some((_, Week(.., Days(days)), week_spans)) = dated_weeks.iter()
+        .find(|(date, ..)| *date == Some(starting_date)) else {
+            return match dated_weeks.iter().find(|(date, ..)| date.is_some_and(|date| starting_date < date)) {
+                Some((.., later)) => {
+                    let at = later.span.start;
+                    edited(splice(code, at, at, &format!("{week_text}{nl}")))
+                },
+
+                None => {
+                    let at = timesheets.weeks.last().map(|last| last.spans.span.end).unwrap_or(code.len());
+                    edited(splice(code, at, at, &format!("{nl}{week_text}")))
+                },
+            };
+        };
+
+    let dated_days: Vec<_> = days.iter()
+        .zip(&week_spans.days)
+        .map(|(day, day_spans)| (day.date(starting_date), day_spans))
+        .collect();
+
+    match dated_days.iter().find(|(date, _)| today <= *date) {
+        Some((date, today_spans)) if *date == today => {
+            let at = today_spans.span.end;
+            edited(splice(code, at, at, &log_text))
+        },
+
+        Some((_, later)) => {
+            let at = later.span.start;
+            edited(splice(code, at, at, &format!("{day_text}{nl}")))
+        },
+
+        None => {
+            let at = week_spans.span.end;
+            edited(splice(code, at, at, &format!("{nl}{day_text}")))
+        },
+    }
+}
+
+/// Closes the most recent log of the given type still ending at NOW.
+/// Without an end time, the current time is used, marked as the next day for a log left open overnight
+pub fn end_log(code: &str, log_type: &LogType, end: Option<TimeRangeEnd>, ctx: &EvalContext) -> Result<Edited, EditError> {
+    let timesheets = parse_timesheets(code)
+        .map_err(EditError::Parsing)?;
+
+    let OpenLog { log: Log(event), spans, date } = open_logs(&timesheets, log_type, ctx)
+        .pop()
+        .ok_or(EditError::NothingOpen(log_type.keyword()))?;
+
+    let today = ctx.today();
+    let date = date.unwrap_or(today);
+
+    // Either today's, or yesterday's still running overnight
+    let still_open = matches!(event.time_period(), Some(TimePeriod::TimeRange(range)) if range.is_open_on(date, ctx));
+
+    let end = match end {
+        Some(TimeRangeEnd::Now(_)) => return Err(EditError::EndAtNow),
+        Some(end) => end,
+        None if date == today => TimeRangeEnd::Time(Time(ctx.time())),
+        None if still_open => TimeRangeEnd::NextDay(NextDayTime(Time(ctx.time()))),
+        None => return Err(EditError::EndTimeNeeded { log: event.to_string(), line: spans.span.line }),
+    };
+
+    let Some(now_span) = spans.time_range_end else {
+        unreachable!("Open logs always end in a time range")
+    };
+
+    let code = splice(code, now_span.start, now_span.end, &end.to_string());
+    let line = code[spans.span.start..now_span.start].to_string() + &end.to_string();
+
+    Ok(Edited { code, line, date })
+}
+
+#[test]
+fn check_edits_preserve_the_rest_of_the_file() {
+    let code = "\nWEEK 16th September 2024\n  Monday\n    WORKING DAY 09:00 - 17:00 |  odd  spacing kept \n\n  Wednesday\n    LUNCH 1h\n\n\n";
+    let ctx = EvalContext::at_utc("2024-09-17 08:48");
+
+    let started = start_log(code, &LogType::WorkingDay, Time(ctx.time()), None, &ctx).unwrap();
+
+    assert_eq!(
+        started.code,
+        "\nWEEK 16th September 2024\n  Monday\n    WORKING DAY 09:00 - 17:00 |  odd  spacing kept \n\n  Tuesday\n    WORKING DAY 08:48 - NOW\n\n  Wednesday\n    LUNCH 1h\n\n\n"
+    );
+
+    let ended = end_log(&started.code, &LogType::WorkingDay, Some("17:30".parse().unwrap()), &ctx).unwrap();
+
+    assert_eq!(ended.code, started.code.replace("08:48 - NOW", "08:48 - 17:30"));
+    assert_eq!(ended.line, "WORKING DAY 08:48 - 17:30");
+}
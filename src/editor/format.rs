@@ -0,0 +1,87 @@
+use thiserror::Error;
+
+use crate::parser::*;
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("{0}")]
+    Parsing(ParsingError),
+
+    #[error("The formatted timesheet doesn't read back the same as the original, so it has been left untouched")]
+    RoundTrip,
+}
+
+fn normalise_summary(summary: &mut Option<Summary>) {
+    *summary = summary.take()
+        .map(|Summary(summary)| summary.trim().to_string())
+        .filter(|summary| !summary.is_empty())
+        .map(Summary);
+}
+
+/// Rewrites each period in its normal form, and drops commentary left blank
+fn normalise(Weeks(_, balance_forward, contracts, allowances, weeks): &mut Weeks) {
+    let balances_forward = balance_forward.iter_mut()
+        .chain(weeks.iter_mut().flat_map(|Week(_, balance_forward, _)| balance_forward.iter_mut()));
+
+    for BalanceForward(_, period, summary) in balances_forward {
+        *period = period.normalised();
+        normalise_summary(summary);
+    }
+
+    for Contract(_, summary, days) in contracts.iter_mut() {
+        for ContractDay(_, period) in days.iter_mut() {
+            *period = period.normalised();
+        }
+
+        normalise_summary(summary);
+    }
+
+    for Allowance(.., summary) in allowances.iter_mut() {
+        normalise_summary(summary);
+    }
+
+    let logs = weeks.iter_mut()
+        .flat_map(|Week(_, _, Days(days))| days.iter_mut())
+        .flat_map(|Day(_, logs)| logs.iter_mut());
+
+    for Log(event) in logs {
+        let (time_period, summary) = event.parts_mut();
+
+        if let Some(TimePeriod::Period(period)) = time_period {
+            *period = period.normalised();
+        }
+
+        normalise_summary(summary);
+    }
+}
+
+/// The timesheet in canonical layout.
+/// Reading the result back must give exactly the AST it was printed from, or nothing is returned
+pub fn format_timesheet(code: &str) -> Result<String, FormatError> {
+    let mut weeks = parse_timesheets(code)
+        .map_err(FormatError::Parsing)?
+        .into_weeks();
+
+    normalise(&mut weeks);
+
+    let formatted = weeks.to_string();
+
+    match parse_timesheets(&formatted).map(Timesheets::into_weeks) {
+        Ok(reparsed) if reparsed == weeks => Ok(formatted),
+        _ => Err(FormatError::RoundTrip),
+    }
+}
+
+#[test]
+fn check_format_is_canonical() {
+    let code = "\n\nBALANCE FORWARD 90m | from 2023\n\nWEEK 2024-09-16\n  BALANCE FORWARD -0h 45m\n  Monday\n    WORKING DAY 09:00 - 17:00\n    BREAK 90m |  Dentist  \n    BREAK 10m | Coffee\n    LUNCH 0h 45m\n\n  Tuesday\n    WORK 1h 0m | Deploy\n\n";
+
+    let formatted = format_timesheet(code).unwrap();
+
+    assert_eq!(
+        formatted,
+        "BALANCE FORWARD +1h 30m | from 2023\n\nWEEK 2024-09-16\n  BALANCE FORWARD -45m\n  Monday\n    WORKING DAY 09:00 - 17:00\n    BREAK 1h 30m | Dentist\n    BREAK 10m    | Coffee\n    LUNCH 45m\n\n  Tuesday\n    WORK 1h | Deploy\n"
+    );
+
+    assert_eq!(format_timesheet(&formatted).unwrap(), formatted);
+}
@@ -0,0 +1,5 @@
+mod edit;
+mod format;
+
+pub use edit::{end_log, start_log, EditError, Edited};
+pub use format::{format_timesheet, FormatError};
@@ -0,0 +1,62 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+use crate::config::Config;
+
+/// Everything evaluation and rendering depend on besides the timesheet itself.
+/// The current time is captured once, so that every `NOW` and every deadline in a run agree.
+#[derive(Debug, Clone)]
+pub struct EvalContext {
+    pub now: DateTime<FixedOffset>,
+    pub config: Config,
+}
+
+impl EvalContext {
+    pub fn new(config: Config) -> Self {
+        EvalContext {
+            now: Local::now().fixed_offset(),
+            config,
+        }
+    }
+
+    /// Evaluates as though it were the given local time, e.g. for `--at "2024-09-24 12:00"`.
+    /// Falls back to UTC for times skipped over by a daylight saving change.
+    pub fn at(at: NaiveDateTime, config: Config) -> Self {
+        let now = Local.from_local_datetime(&at)
+            .earliest()
+            .map(|now| now.fixed_offset())
+            .unwrap_or_else(|| at.and_utc().fixed_offset());
+
+        EvalContext { now, config }
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.now.date_naive()
+    }
+
+    pub fn time(&self) -> NaiveTime {
+        self.now.time()
+    }
+}
+
+#[cfg(test)]
+impl EvalContext {
+    /// At the given time in UTC, e.g. "2026-10-16 12:00", with the default config,
+    /// so that tests give the same results in any time zone
+    pub fn at_utc(at: &str) -> Self {
+        let at = NaiveDateTime::parse_from_str(at, "%Y-%m-%d %H:%M")
+            .expect("Test times are written as e.g. \"2026-10-16 12:00\"");
+
+        EvalContext {
+            now: at.and_utc().fixed_offset(),
+            config: Config::default(),
+        }
+    }
+}
+
+/// Parses and evaluates a timesheet that's expected to be valid
+#[cfg(test)]
+pub fn evaluate_str(code: &str, ctx: &EvalContext) -> crate::evaluator::TotalDelta {
+    use crate::{evaluator::evaluate_timesheets, parser::parse_timesheets};
+
+    evaluate_timesheets(parse_timesheets(code).unwrap(), ctx).unwrap()
+}
@@ -0,0 +1,117 @@
+use std::ops::Not;
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+
+use crate::evaluator::{eval::{TotalDelta, WorkingDayRange}, EvalContext};
+
+/// When work can stop today, given the balance so far
+pub struct Deadlines {
+    /// Nothing has been logged today, so the deadlines assume a start now
+    pub not_started: bool,
+    /// Today's lunch, if it hasn't been logged yet
+    pub lunch_if_not_taken: Option<TimeDelta>,
+    /// When the total balance reaches zero
+    pub earliest_finish: DateTime<FixedOffset>,
+    /// When today's balance reaches zero, leaving the credit from earlier days untouched
+    pub retain_credit: DateTime<FixedOffset>,
+    now: DateTime<FixedOffset>,
+}
+
+impl Deadlines {
+    /// Worked out from the start of today's WORKING DAY, as though it carries on until the deadline,
+    /// with the time from every other log (breaks so far, extra WORK, and so on) taken into account.
+    /// Without a WORKING DAY range to go from, work is assumed to carry on from now
+    pub fn new(total_delta: &TotalDelta, ctx: &EvalContext) -> Self {
+        let today = total_delta.today();
+        let now = ctx.now;
+
+        let (earliest_finish, retain_credit) = match &today.working_day {
+            Some(WorkingDayRange { start, counted }) => {
+                // Everything counted today besides the WORKING DAY itself
+                let rest = today.actual - *counted;
+                let start = now + (*start - now.naive_local());
+
+                (
+                    start + (today.expected - total_delta.total_delta_excluding_today - rest),
+                    start + (today.expected - rest),
+                )
+            },
+
+            None => (now - total_delta.total_delta, now - today.delta),
+        };
+
+        Deadlines {
+            not_started: !today.logged,
+            lunch_if_not_taken: today.had_lunch.not().then_some(ctx.config.lunch),
+            earliest_finish,
+            retain_credit,
+            now,
+        }
+    }
+
+    /// The deadline pushed back by a lunch still to be taken
+    pub fn with_lunch(&self, deadline: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        self.lunch_if_not_taken
+            .map(|lunch| deadline + lunch)
+    }
+
+    pub fn has_passed(&self, deadline: DateTime<FixedOffset>) -> bool {
+        deadline < self.now
+    }
+}
+
+/// e.g. `17:30`
+#[cfg(test)]
+fn time_of_day(deadline: DateTime<FixedOffset>) -> String {
+    deadline.format("%H:%M").to_string()
+}
+
+#[test]
+fn check_deadlines_without_a_start_go_from_now() {
+    use crate::evaluator::context::evaluate_str;
+
+    let ctx = EvalContext::at_utc("2026-10-16 12:00");
+    let total_delta = evaluate_str("WEEK 2026-10-12\n  Thursday\n    WORKING DAY 09:00 - 18:00\n", &ctx);
+    let deadlines = Deadlines::new(&total_delta, &ctx);
+
+    assert!(deadlines.not_started);
+    assert_eq!((time_of_day(deadlines.earliest_finish), time_of_day(deadlines.retain_credit)), ("19:00".into(), "20:00".into()));
+}
+
+#[test]
+fn check_deadlines_go_from_the_start_of_the_working_day() {
+    use crate::evaluator::context::evaluate_str;
+
+    let ctx = EvalContext::at_utc("2026-10-16 12:00");
+    let total_delta = evaluate_str("WEEK 2026-10-12\n  Thursday\n    WORKING DAY 09:00 - 18:00\n\n  Friday\n    WORKING DAY 09:00 - NOW\n", &ctx);
+    let deadlines = Deadlines::new(&total_delta, &ctx);
+
+    assert!(!deadlines.not_started);
+    assert_eq!(deadlines.lunch_if_not_taken, Some(TimeDelta::minutes(30)));
+    assert_eq!((time_of_day(deadlines.earliest_finish), time_of_day(deadlines.retain_credit)), ("16:00".into(), "17:00".into()));
+}
+
+#[test]
+fn check_deadlines_take_a_break_still_going_as_ending_now() {
+    use crate::evaluator::context::evaluate_str;
+
+    let ctx = EvalContext::at_utc("2026-10-16 12:00");
+    let total_delta = evaluate_str("WEEK 2026-10-12\n  Friday\n    WORKING DAY 09:00 - NOW\n    BREAK 11:30 - NOW | Errand\n", &ctx);
+    let deadlines = Deadlines::new(&total_delta, &ctx);
+
+    assert_eq!((time_of_day(deadlines.earliest_finish), time_of_day(deadlines.retain_credit)), ("17:30".into(), "17:30".into()));
+}
+
+#[test]
+fn check_deadlines_already_passed() {
+    use crate::evaluator::context::evaluate_str;
+
+    let ctx = EvalContext::at_utc("2026-10-16 18:00");
+    let total_delta = evaluate_str("WEEK 2026-10-12\n  Friday\n    WORKING DAY 09:00 - NOW\n    LUNCH 30m\n", &ctx);
+    let deadlines = Deadlines::new(&total_delta, &ctx);
+
+    assert_eq!(deadlines.lunch_if_not_taken, None);
+    assert_eq!(time_of_day(deadlines.earliest_finish), "17:30");
+    assert!(deadlines.has_passed(deadlines.earliest_finish));
+    assert!(!deadlines.has_passed(deadlines.now + TimeDelta::minutes(1)));
+}
@@ -1,33 +1,253 @@
-use chrono::{Local, TimeDelta};
-use once_cell::sync::Lazy;
+use std::{cmp, iter::Sum, ops::{Add, Neg}};
 
-use crate::parser::*;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Weekday};
+use thiserror::Error;
 
-// TODO this should be configuration
-static WORKING_DAY_DURATION: Lazy<TimeDelta> 
-    = Lazy::new(|| TimeDelta::hours(8) + TimeDelta::minutes(00));
+use crate::{config::{Overnight, Schedule}, evaluator::EvalContext, parser::*, utils::diagnostic::{Diagnostic, Severity}};
+
+#[derive(Error, Debug)]
+pub enum EvalError {
+    #[error("WEEK {0} is not a valid date")]
+    InvalidDate(String, Span),
+
+    #[error("WEEK {0} starts on a {1}, but weeks are configured to start on a {2}")]
+    WrongWeekStart(String, DayName, DayName, Span),
+
+    #[error("{day} {date}: '{log}' ends at NOW, which is only allowed on today's date, or yesterday's overnight", date = LongDate::from(*.date))]
+    NowOutsideToday { day: DayName, date: NaiveDate, log: String, span: Span },
+
+    #[error("{0} appears more than once in WEEK {1}")]
+    DuplicateDay(DayName, String, Span, Span),
+
+    #[error("'{0}' is missing its commentary")]
+    MissingSummary(String, Span),
+
+    #[error("'{0}' starts and ends at the same time")]
+    EmptyRange(String, Span),
+
+    #[error("CONTRACT FROM {0} is not a valid date")]
+    InvalidContractDate(String, Span),
+
+    #[error("More than one CONTRACT starts on {date}", date = LongDate::from(*.0))]
+    DuplicateContract(NaiveDate, Span, Span),
+
+    #[error("{0} appears more than once in CONTRACT FROM {1}")]
+    DuplicateContractDay(DayName, String, Span),
+
+    #[error("{0} is not a valid date, in '{1}'")]
+    InvalidAllowanceDate(String, String, Span),
+
+    #[error("The leave year from {date} is given more than one ALLOWANCE", date = LongDate::from(*.0))]
+    DuplicateAllowance(NaiveDate, Span, Span),
+
+    #[error("'{0}' has its carry over expire before the leave year starts")]
+    CarryOverExpiresEarly(String, Span),
+}
+
+impl EvalError {
+    pub fn span(&self) -> Span {
+        match self {
+            EvalError::InvalidDate(_, span)
+            | EvalError::WrongWeekStart(_, _, _, span)
+            | EvalError::NowOutsideToday { span, .. }
+            | EvalError::DuplicateDay(_, _, span, _)
+            | EvalError::MissingSummary(_, span)
+            | EvalError::EmptyRange(_, span)
+            | EvalError::InvalidContractDate(_, span)
+            | EvalError::DuplicateContract(_, span, _)
+            | EvalError::DuplicateContractDay(_, _, span)
+            | EvalError::InvalidAllowanceDate(_, _, span)
+            | EvalError::DuplicateAllowance(_, span, _)
+            | EvalError::CarryOverExpiresEarly(_, span) => *span,
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            EvalError::InvalidDate(..) =>
+                None,
+
+            EvalError::WrongWeekStart(_, _, week_start, _) =>
+                Some(format!("use the date of the {week_start} that starts the week, or change week_start in the config")),
+
+            EvalError::NowOutsideToday { .. } =>
+                Some("replace NOW with the time this ended".into()),
+
+            EvalError::DuplicateDay(day, _, _, first) =>
+                Some(format!("move these logs under the earlier {day}, on line {}", first.line)),
+
+            EvalError::MissingSummary(..) =>
+                Some("WORK and BREAK must say what they were for, e.g. 'BREAK 15m | Dentist'".into()),
+
+            EvalError::EmptyRange(..) =>
+                Some("for a range of 24 hours, mark the end as being on the next day, e.g. '09:00 - 09:00+1'".into()),
+
+            EvalError::InvalidContractDate(..) =>
+                None,
+
+            EvalError::DuplicateContract(_, _, first) =>
+                Some(format!("keep only the CONTRACT on line {}", first.line)),
+
+            EvalError::DuplicateContractDay(..) =>
+                Some("give each day its time once, leaving out the days with nothing expected of them".into()),
+
+            EvalError::InvalidAllowanceDate(..) =>
+                None,
+
+            EvalError::DuplicateAllowance(_, _, first) =>
+                Some(format!("keep only the ALLOWANCE on line {}", first.line)),
+
+            EvalError::CarryOverExpiresEarly(..) =>
+                Some("the UNTIL date should fall within the leave year, which starts on the FROM date".into()),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: self.to_string(),
+            span: self.span(),
+            hint: self.hint(),
+        }
+    }
+}
+
+pub struct LogDelta {
+    /// The log as written, without its commentary
+    pub event: String,
+    pub summary: Option<String>,
+    pub delta: TimeDelta,
+}
 
 pub struct DayDelta {
-    pub weekday: String,
+    pub date: NaiveDate,
+    pub expected: TimeDelta,
+    /// The time counted towards the day so far, including any time off credited to it
+    pub actual: TimeDelta,
     pub delta: TimeDelta,
     pub had_lunch: bool,
+    /// False for a placeholder standing in for today, before anything has been logged
+    pub logged: bool,
+    /// Some of the logged time lies beyond the current time, so hasn't been counted
+    pub has_future_time: bool,
+    /// The last WORKING DAY given as a range, which finish times are worked out from
+    pub working_day: Option<WorkingDayRange>,
+    pub absence: Absence,
+    /// The name of the public holiday falling on the day
+    pub holiday: Option<String>,
+    pub log_deltas: Vec<LogDelta>,
+}
+
+/// Time off, by its kind, as each is accounted for separately
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Absence {
+    pub leave: TimeDelta,
+    pub sick: TimeDelta,
+    pub holiday: TimeDelta,
+    /// Unlike the others, this is drawn from the balance rather than credited
+    pub toil: TimeDelta,
+}
+
+impl Add for Absence {
+    type Output = Absence;
+
+    fn add(self, other: Absence) -> Absence {
+        Absence {
+            leave: self.leave + other.leave,
+            sick: self.sick + other.sick,
+            holiday: self.holiday + other.holiday,
+            toil: self.toil + other.toil,
+        }
+    }
+}
+
+impl Sum for Absence {
+    fn sum<I: Iterator<Item = Absence>>(iter: I) -> Absence {
+        iter.fold(Absence::default(), Add::add)
+    }
+}
+
+pub struct WorkingDayRange {
+    pub start: NaiveDateTime,
+    /// The time counted towards the day so far, which stops at the current time
+    pub counted: TimeDelta,
+}
+
+/// A balance carried in from elsewhere, such as the previous year's timesheet
+pub struct BalanceForwardDelta {
+    pub delta: TimeDelta,
+    pub summary: Option<String>,
+}
+
+/// Leave left over from the year before
+pub struct CarryOverDelta {
+    pub days: f64,
+    pub expires: Option<NaiveDate>,
+}
+
+/// The days of leave for the year from the given date
+pub struct AllowanceDelta {
+    pub start: NaiveDate,
+    pub days: f64,
+    pub carry_over: Option<CarryOverDelta>,
+    pub summary: Option<String>,
 }
 
 pub struct WeekDelta {
-    pub starting_date: String,
+    pub starting_date: NaiveDate,
+    /// Only counts the week's days, leaving out any balance forward
     pub week_delta: TimeDelta,
+    pub balance_forward: Option<BalanceForwardDelta>,
     pub day_deltas: Vec<DayDelta>,
 }
 
+impl WeekDelta {
+    /// What the week adds to the total, including any balance forward
+    pub fn total_delta(&self) -> TimeDelta {
+        self.week_delta + self.balance_forward.as_ref().map_or(TimeDelta::zero(), |balance| balance.delta)
+    }
+}
+
 pub struct TotalDelta {
+    /// Including every balance forward
     pub total_delta: TimeDelta,
+    /// From the top of the file, ahead of every week
+    pub balance_forward: Option<BalanceForwardDelta>,
     pub total_delta_excluding_today: TimeDelta,
     pub week_deltas: Vec<WeekDelta>,
+    /// In date order, with no two starting on the same date
+    pub allowances: Vec<AllowanceDelta>,
+    pub today: NaiveDate,
+}
+
+impl TotalDelta {
+    /// Always present, as evaluation inserts an empty day for today if it hasn't been logged
+    pub fn today(&self) -> &DayDelta {
+        self.day(self.today)
+            .expect("Evaluation inserts today")
+    }
+
+    pub fn day(&self, date: NaiveDate) -> Option<&DayDelta> {
+        self.week_deltas.iter()
+            .flat_map(|week| &week.day_deltas)
+            .find(|day| day.date == date)
+    }
+
+    /// Across every week, not only those shown, up to and including today.
+    /// Absence on later days is only booked, and the leave ledger counts it as such
+    pub fn absence(&self) -> Absence {
+        self.week_deltas.iter()
+            .flat_map(|week| &week.day_deltas)
+            .filter(|day| day.date <= self.today)
+            .map(|day| day.absence)
+            .sum()
+    }
 }
 
 
 impl Period {
-    fn evaluate(self) -> TimeDelta {
+    /// Days and half days are measured in the expected hours of the day they're taken on
+    pub(crate) fn evaluate(self, expected: TimeDelta) -> TimeDelta {
         match self {
             Period::HoursMinutes(HoursMinutes(hours, Some(minutes))) =>
                 TimeDelta::hours(*hours) + TimeDelta::minutes(*minutes),
@@ -37,107 +257,801 @@ impl Period {
 
             Period::Minutes(minutes) =>
                 TimeDelta::minutes(*minutes),
+
+            Period::Days(DayCount(Decimal(days))) =>
+                TimeDelta::seconds((expected.num_seconds() as f64 * days).round() as i64),
+
+            Period::HalfDay(_) =>
+                expected / 2,
         }
     }
+
+    /// `None` for days and half days, which only have a length on a particular day
+    pub(crate) fn fixed(&self) -> Option<TimeDelta> {
+        match self {
+            Period::Days(_) | Period::HalfDay(_) => None,
+            period => Some(period.clone().evaluate(TimeDelta::zero())),
+        }
+    }
+}
+
+/// Time counted towards a day by one of its logs
+#[derive(Clone, Copy, Default)]
+struct Counted {
+    delta: TimeDelta,
+    /// Time past midnight, when overnight ranges are configured to be split across days
+    next_day_delta: TimeDelta,
+    /// Part of the log lies beyond the current time, and was left out of the delta
+    has_future_time: bool,
+    absence: Absence,
+}
+
+impl From<TimeDelta> for Counted {
+    fn from(delta: TimeDelta) -> Self {
+        Counted { delta, ..Counted::default() }
+    }
 }
 
+impl Counted {
+    /// The same time, also recorded as time off of the given kind
+    fn as_absence(self, kind: fn(&mut Absence) -> &mut TimeDelta) -> Counted {
+        let mut absence = Absence::default();
+        *kind(&mut absence) = self.delta + self.next_day_delta;
+
+        Counted { absence, ..self }
+    }
+}
+
+impl Neg for Counted {
+    type Output = Counted;
+
+    fn neg(self) -> Self::Output {
+        Counted { delta: -self.delta, next_day_delta: -self.next_day_delta, ..self }
+    }
+}
+
+/// The earliest hour a range can start at and still be left open past midnight, such as for a night shift
+const OVERNIGHT_FROM_HOUR: u32 = 12;
+
 impl TimeRange {
-    fn evaluate(self) -> TimeDelta {
+    /// The start and end of the range on the given date.
+    /// An end that comes before the start, or that is marked `+1`, falls on the following day
+    pub(crate) fn resolve(&self, date: NaiveDate, ctx: &EvalContext) -> (NaiveDateTime, NaiveDateTime) {
         let TimeRange(Time(start), end) = self;
+        let next_day = date + TimeDelta::days(1);
 
         let end = match end {
-            TimeRangeEnd::Time(Time(end)) =>
-                end,
-                // cmp::min(end, now), // Should be capped at 'now' if evaluating the current day
-                // Sadly that information is out of scope at the moment
+            TimeRangeEnd::Time(Time(end)) if end < start => next_day.and_time(*end),
+            TimeRangeEnd::Time(Time(end)) => date.and_time(*end),
+            TimeRangeEnd::NextDay(NextDayTime(Time(end))) => next_day.and_time(*end),
+            TimeRangeEnd::Now(_) => ctx.now.naive_local(),
+        };
+
+        (date.and_time(*start), end)
+    }
 
-            TimeRangeEnd::Now(_) =>
-                Local::now().time(),
+    /// NOW belongs to today, or to yesterday for an overnight range that hasn't reached midnight's other side yet.
+    /// Only ranges started from midday onwards can be running overnight, as a range started yesterday morning
+    /// would otherwise pass for one until the clock caught up with its start
+    pub(crate) fn is_open_on(&self, date: NaiveDate, ctx: &EvalContext) -> bool {
+        let TimeRange(Time(start), _) = self;
+
+        date == ctx.today()
+            || (date + TimeDelta::days(1) == ctx.today() && OVERNIGHT_FROM_HOUR <= start.hour() && ctx.time() < *start)
+    }
+
+    /// Only time that has already passed is counted.
+    /// NOW is assumed to be valid on the given date, which `Day::evaluate` checks beforehand
+    fn evaluate(self, date: NaiveDate, ctx: &EvalContext) -> Counted {
+        let (start, end) = self.resolve(date, ctx);
+        let now = ctx.now.naive_local();
+        let midnight = (date + TimeDelta::days(1)).and_time(NaiveTime::MIN);
+
+        let counted_start = cmp::min(start, now);
+        let counted_end = cmp::min(end, now);
+        let delta = counted_end - counted_start;
+
+        let next_day_delta = match ctx.config.overnight {
+            Overnight::StartDay => TimeDelta::zero(),
+            Overnight::Split => counted_end - cmp::max(counted_start, midnight),
         };
 
-        end - start
+        Counted {
+            delta: delta - next_day_delta.max(TimeDelta::zero()),
+            next_day_delta: next_day_delta.max(TimeDelta::zero()),
+            has_future_time: now < end,
+            ..Counted::default()
+        }
     }
 }
 
 impl TimePeriod {
-    fn evaluate(self) -> TimeDelta {
+    fn evaluate(self, date: NaiveDate, expected: TimeDelta, ctx: &EvalContext) -> Counted {
         match self {
-            TimePeriod::Period(period) => period.evaluate(),
-            TimePeriod::TimeRange(time_range) => time_range.evaluate(),
+            TimePeriod::Period(period) => Counted::from(period.evaluate(expected)),
+            TimePeriod::TimeRange(time_range) => time_range.evaluate(date, ctx),
         }
     }
+
+    /// Ends at NOW, on a date where NOW can't be
+    fn is_stale(&self, date: NaiveDate, ctx: &EvalContext) -> bool {
+        match self {
+            TimePeriod::TimeRange(range @ TimeRange(_, TimeRangeEnd::Now(_))) => !range.is_open_on(date, ctx),
+            _ => false,
+        }
+    }
+
+    fn is_empty_range(&self) -> bool {
+        matches!(self, TimePeriod::TimeRange(TimeRange(Time(start), TimeRangeEnd::Time(Time(end)))) if start == end)
+    }
 }
 
 impl Log {
-    fn evaluate(self) -> TimeDelta {
+    fn evaluate(self, date: NaiveDate, expected: TimeDelta, ctx: &EvalContext) -> Counted {
         let Log(event) = self;
 
+        // Without a time period, a day off takes up all of the day's expected hours
+        let day_off = |period: Option<TimePeriod>| period
+            .map_or(Counted::from(expected), |period| period.evaluate(date, expected, ctx));
+
         match event {
-            LogEvent::Break(BreakLog(period)) => -period.evaluate(),
-            LogEvent::Leave(LeaveLog(period)) => period.evaluate(),
-            LogEvent::Lunch(LunchLog(period)) => -period.evaluate(),
-            LogEvent::Work(WorkLog(period)) => period.evaluate(),
-            LogEvent::WorkingDay(WorkingDayLog(period)) => period.evaluate(),
+            LogEvent::Break(BreakLog(period, _)) => -period.evaluate(date, expected, ctx),
+            LogEvent::Leave(LeaveLog(period, _)) => period.evaluate(date, expected, ctx).as_absence(|absence| &mut absence.leave),
+            LogEvent::Lunch(LunchLog(period, _)) => -period.evaluate(date, expected, ctx),
+            LogEvent::Work(WorkLog(period, _)) => period.evaluate(date, expected, ctx),
+            LogEvent::WorkingDay(WorkingDayLog(period, _)) => period.evaluate(date, expected, ctx),
+            LogEvent::Sick(SickLog(period, _)) => day_off(period).as_absence(|absence| &mut absence.sick),
+            LogEvent::Holiday(HolidayLog(period, _)) => day_off(period).as_absence(|absence| &mut absence.holiday),
+
+            // Paid for out of the balance, so counts for nothing towards the day itself
+            LogEvent::Toil(ToilLog(period, _)) => Counted {
+                delta: TimeDelta::zero(),
+                next_day_delta: TimeDelta::zero(),
+                ..day_off(period).as_absence(|absence| &mut absence.toil)
+            },
+        }
+    }
+}
+
+impl BalanceForward {
+    fn evaluate(self) -> BalanceForwardDelta {
+        let BalanceForward(negative, period, summary) = self;
+        let delta = period.fixed()
+            .expect("The grammar only allows hours and minutes");
+
+        BalanceForwardDelta {
+            delta: match negative {
+                Some(Negative) => -delta,
+                None => delta,
+            },
+            summary: summary.map(|Summary(summary)| summary.trim().to_owned()),
+        }
+    }
+}
+
+impl Contract {
+    /// The date it starts from, and the time it expects on each day of the week
+    fn evaluate(self, span: Span) -> Result<(NaiveDate, Schedule), EvalError> {
+        let Contract(date, _, days) = self;
+
+        let from = date.to_naive_date()
+            .ok_or_else(|| EvalError::InvalidContractDate(date.to_string(), span))?;
+
+        let mut schedule = Schedule::empty();
+        let mut seen = vec![];
+
+        for ContractDay(day_name @ DayName(weekday), period) in days {
+            if seen.contains(&weekday) {
+                return Err(EvalError::DuplicateContractDay(day_name, date.to_string(), span));
+            }
+
+            seen.push(weekday);
+            schedule.set(weekday, period.fixed().expect("The grammar only allows hours and minutes"));
+        }
+
+        Ok((from, schedule))
+    }
+}
+
+fn eval_contracts(contracts: Vec<Spanned<Contract>>) -> Result<Vec<(NaiveDate, Schedule)>, Vec<EvalError>> {
+    let mut errors = vec![];
+    let mut evaluated: Vec<(NaiveDate, Schedule, Span)> = vec![];
+
+    for Spanned { node: contract, spans: span } in contracts {
+        match contract.evaluate(span) {
+            Ok((from, schedule)) => match evaluated.iter().find(|(first, ..)| *first == from) {
+                Some((_, _, first_span)) => errors.push(EvalError::DuplicateContract(from, span, *first_span)),
+                None => evaluated.push((from, schedule, span)),
+            },
+
+            Err(err) => errors.push(err),
+        }
+    }
+
+    match errors.is_empty() {
+        true => Ok(evaluated.into_iter().map(|(from, schedule, _)| (from, schedule)).collect()),
+        false => Err(errors),
+    }
+}
+
+impl Allowance {
+    fn evaluate(self, span: Span) -> Result<AllowanceDelta, EvalError> {
+        let to_naive_date = |date: &Date| date.to_naive_date()
+            .ok_or_else(|| EvalError::InvalidAllowanceDate(date.to_string(), self.to_string(), span));
+
+        let Allowance(DayCount(Decimal(days)), date, carry_over, summary) = &self;
+        let start = to_naive_date(date)?;
+
+        let carry_over = match carry_over {
+            Some(CarryOver(DayCount(Decimal(days)), expiry)) => {
+                let expires = expiry.as_ref()
+                    .map(|Expiry(date)| to_naive_date(date))
+                    .transpose()?;
+
+                if expires.is_some_and(|expires| expires < start) {
+                    return Err(EvalError::CarryOverExpiresEarly(self.to_string(), span));
+                }
+
+                Some(CarryOverDelta { days: *days, expires })
+            },
+
+            None => None,
+        };
+
+        Ok(AllowanceDelta {
+            start,
+            days: *days,
+            carry_over,
+            summary: summary.as_ref().map(|Summary(summary)| summary.trim().to_owned()),
+        })
+    }
+}
+
+/// Sorted by the date each leave year starts, as they may have been gathered from several files
+fn eval_allowances(allowances: Vec<Spanned<Allowance>>) -> Result<Vec<AllowanceDelta>, Vec<EvalError>> {
+    let mut errors = vec![];
+    let mut evaluated: Vec<(AllowanceDelta, Span)> = vec![];
+
+    for Spanned { node: allowance, spans: span } in allowances {
+        match allowance.evaluate(span) {
+            Ok(allowance) => match evaluated.iter().find(|(first, _)| first.start == allowance.start) {
+                Some((_, first_span)) => errors.push(EvalError::DuplicateAllowance(allowance.start, span, *first_span)),
+                None => evaluated.push((allowance, span)),
+            },
+
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    evaluated.sort_by_key(|(allowance, _)| allowance.start);
+
+    Ok(evaluated.into_iter()
+        .map(|(allowance, _)| allowance)
+        .collect())
+}
+
+impl Week {
+    /// Rejects dates that don't exist, or that don't fall on the configured first day of the week
+    pub(crate) fn starting_date(&self, week_start: Weekday, spans: &WeekSpans) -> Result<NaiveDate, EvalError> {
+        let Week(date, ..) = self;
+
+        let starting_date = date.to_naive_date()
+            .ok_or_else(|| EvalError::InvalidDate(date.to_string(), spans.date))?;
+
+        match starting_date.weekday() == week_start {
+            true => Ok(starting_date),
+            false => Err(EvalError::WrongWeekStart(date.to_string(), DayName(starting_date.weekday()), DayName(week_start), spans.date)),
         }
     }
 }
 
 impl Day {
-    fn evaluate(self) -> DayDelta {
-        let Day(DayName(weekday), logs) = self;
+    /// The calendar date of this day, given the date its week starts on
+    pub(crate) fn date(&self, starting_date: NaiveDate) -> NaiveDate {
+        let Day(DayName(weekday), _) = self;
+
+        let offset = (7 + weekday.num_days_from_monday() - starting_date.weekday().num_days_from_monday()) % 7;
+
+        starting_date + TimeDelta::days(offset.into())
+    }
+
+    /// Every problem with the day's logs, rather than just the first
+    fn check(&self, date: NaiveDate, spans: &DaySpans, ctx: &EvalContext) -> Vec<EvalError> {
+        let Day(day_name, logs) = self;
+
+        logs.iter()
+            .zip(&spans.logs)
+            .flat_map(|(Log(event), log_spans)| {
+                let period = event.time_period();
+
+                // A NOW left open on any other day would keep on counting forever
+                let stale = period.is_some_and(|period| period.is_stale(date, ctx))
+                    .then(|| EvalError::NowOutsideToday { day: *day_name, date, log: event.to_string(), span: log_spans.span });
+
+                let empty = period.is_some_and(TimePeriod::is_empty_range)
+                    .then(|| EvalError::EmptyRange(event.to_string(), log_spans.time_period));
+
+                let missing_summary = matches!(event, LogEvent::Work(_) | LogEvent::Break(_))
+                    .then_some(())
+                    .filter(|_| event.summary().is_none_or(str::is_empty))
+                    .map(|_| EvalError::MissingSummary(event.to_string(), log_spans.span));
+
+                [stale, empty, missing_summary]
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Also gives the time carried past midnight into the following day
+    fn evaluate(self, date: NaiveDate, spans: &DaySpans, ctx: &EvalContext) -> Result<(DayDelta, TimeDelta), Vec<EvalError>> {
+        let errors = self.check(date, spans, ctx);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let Day(_, logs) = self;
 
         let had_lunch = logs.iter()
             .any(|log| matches!(log, Log(LogEvent::Lunch(_))));
 
-        let delta = logs.into_iter()
-            .map(Log::evaluate)
+        let last_working_day = logs.iter()
+            .enumerate()
+            .filter_map(|(i, log)| match log {
+                Log(LogEvent::WorkingDay(WorkingDayLog(TimePeriod::TimeRange(TimeRange(Time(start), _)), _))) => Some((*start, i)),
+                _ => None,
+            })
+            .max();
+
+        let expected = ctx.config.expected(date);
+
+        let (log_descriptions, counted): (Vec<_>, Vec<_>) = logs.into_iter()
+            .map(|log| {
+                let Log(event) = &log;
+                let description = (event.to_string(), event.summary().map(str::to_owned));
+
+                (description, log.evaluate(date, expected, ctx))
+            })
+            .unzip();
+
+        let actual = counted.iter()
+            .map(|c| c.delta)
             .sum::<TimeDelta>();
 
-        DayDelta {
+        let next_day_delta = counted.iter()
+            .map(|c| c.next_day_delta)
+            .sum::<TimeDelta>();
+
+        // A day still to come counts for nothing until it arrives, so that planning ahead doesn't run up a deficit.
+        // Its expected hours are kept all the same, for leave booked on it to be measured against
+        let is_future = ctx.today() < date;
+
+        let (actual, delta) = match is_future {
+            true => (TimeDelta::zero(), TimeDelta::zero()),
+            false => (actual, actual - expected),
+        };
+
+        let day_delta = DayDelta {
             had_lunch,
-            weekday,
-            delta: delta - *WORKING_DAY_DURATION
-        }
+            date,
+            expected,
+            actual,
+            delta,
+            logged: true,
+            has_future_time: is_future || counted.iter().any(|c| c.has_future_time),
+            working_day: last_working_day.map(|(start, i)| WorkingDayRange {
+                start: date.and_time(start),
+                counted: counted[i].delta,
+            }),
+            absence: counted.iter()
+                .map(|c| c.absence)
+                .sum(),
+            holiday: ctx.config.holiday(date).map(|holiday| holiday.name.clone()),
+            log_deltas: log_descriptions.into_iter()
+                .zip(&counted)
+                .map(|((event, summary), c)| LogDelta { event, summary, delta: c.delta })
+                .collect(),
+        };
+
+        Ok((day_delta, next_day_delta))
     }
 }
 
-fn eval_week(week: Week) -> WeekDelta {
-    let Week(Date(starting_date), Days(days)) = week;
+/// Time carried past midnight, keyed by the date it was carried into
+type Carried = Vec<(NaiveDate, TimeDelta)>;
+
+fn eval_week(week: Week, spans: &WeekSpans, ctx: &EvalContext) -> Result<(WeekDelta, Carried), Vec<EvalError>> {
+    let starting_date = week.starting_date(ctx.config.week_start, spans)
+        .map_err(|err| vec![err])?;
+
+    let Week(date, balance_forward, Days(days)) = week;
+
+    let mut errors = vec![];
+    let mut first_spans: Vec<(Weekday, Span)> = vec![];
+    let mut evaluated = vec![];
+
+    for (day, day_spans) in days.into_iter().zip(&spans.days) {
+        let Day(day_name @ DayName(weekday), _) = &day;
+
+        match first_spans.iter().find(|(first, _)| first == weekday) {
+            Some((_, first_span)) =>
+                errors.push(EvalError::DuplicateDay(*day_name, date.to_string(), day_spans.day_name, *first_span)),
 
-    let day_deltas = days.into_iter()
-        .map(Day::evaluate)
-        .collect::<Vec<_>>();
+            None =>
+                first_spans.push((*weekday, day_spans.day_name)),
+        }
+
+        let date = day.date(starting_date);
+
+        match day.evaluate(date, day_spans, ctx) {
+            Ok(day_delta) => evaluated.push(day_delta),
+            Err(mut day_errors) => errors.append(&mut day_errors),
+        }
+    }
 
-    WeekDelta {
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let (day_deltas, carried): (Vec<_>, Vec<_>) = evaluated.into_iter()
+        .map(|(day_delta, next_day_delta)| {
+            let next_day = day_delta.date + TimeDelta::days(1);
+            (day_delta, (next_day, next_day_delta))
+        })
+        .unzip();
+
+    let week_delta = WeekDelta {
         starting_date,
         week_delta: day_deltas.iter()
             .map(|d| d.delta)
             .sum(),
+        balance_forward: balance_forward.map(BalanceForward::evaluate),
         day_deltas
+    };
+
+    Ok((week_delta, carried))
+}
+
+/// Places a day into its week, in date order, creating the week if need be
+fn insert_day(week_deltas: &mut Vec<WeekDelta>, day_delta: DayDelta, week_start: Weekday) {
+    let date = day_delta.date;
+    let offset = (7 + date.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+    let starting_date = date - TimeDelta::days(offset.into());
+
+    let week_index = week_deltas.iter()
+        .position(|week| starting_date <= week.starting_date);
+
+    let week = match week_index {
+        Some(index) if week_deltas[index].starting_date == starting_date => &mut week_deltas[index],
+        _ => {
+            let index = week_index.unwrap_or(week_deltas.len());
+            let new_week = WeekDelta {
+                starting_date,
+                week_delta: TimeDelta::zero(),
+                balance_forward: None,
+                day_deltas: vec![],
+            };
+
+            week_deltas.insert(index, new_week);
+            &mut week_deltas[index]
+        }
+    };
+
+    let day_index = week.day_deltas.iter()
+        .position(|day| date < day.date)
+        .unwrap_or(week.day_deltas.len());
+
+    week.week_delta += day_delta.delta;
+    week.day_deltas.insert(day_index, day_delta);
+}
+
+/// Time worked past midnight goes to the following day.
+/// If that day hasn't been logged, it's added with nothing expected of it, as with any other day left out
+fn carry_past_midnight(week_deltas: &mut Vec<WeekDelta>, date: NaiveDate, delta: TimeDelta, ctx: &EvalContext) {
+    let existing = week_deltas.iter_mut()
+        .find_map(|week| {
+            let day = week.day_deltas.iter_mut().find(|day| day.date == date)?;
+            Some((&mut week.week_delta, day))
+        });
+
+    let log_delta = LogDelta {
+        event: "Continued past midnight".into(),
+        summary: None,
+        delta,
+    };
+
+    match existing {
+        Some((week_delta, day)) => {
+            day.actual += delta;
+            day.delta += delta;
+            day.log_deltas.insert(0, log_delta);
+            *week_delta += delta;
+        },
+
+        None => {
+            let day_delta = DayDelta {
+                date,
+                expected: TimeDelta::zero(),
+                actual: delta,
+                delta,
+                had_lunch: false,
+                logged: true,
+                has_future_time: false,
+                working_day: None,
+                absence: Absence::default(),
+                holiday: ctx.config.holiday(date).map(|holiday| holiday.name.clone()),
+                log_deltas: vec![log_delta],
+            };
+
+            insert_day(week_deltas, day_delta, ctx.config.week_start);
+        },
     }
 }
 
-pub fn evaluate_timesheets(weeks: Weeks) -> TotalDelta {
-    let Weeks(weeks) = weeks;
+/// Today is in progress even when nothing has been logged for it yet,
+/// so it gets an empty day of its own, inside a new week if need be
+fn insert_empty_today(week_deltas: &mut Vec<WeekDelta>, ctx: &EvalContext) {
+    let today = ctx.today();
+    let expected = ctx.config.expected(today);
+    let empty_today = DayDelta {
+        date: today,
+        expected,
+        actual: TimeDelta::zero(),
+        delta: -expected,
+        had_lunch: false,
+        logged: false,
+        has_future_time: false,
+        working_day: None,
+        absence: Absence::default(),
+        holiday: ctx.config.holiday(today).map(|holiday| holiday.name.clone()),
+        log_deltas: vec![],
+    };
+
+    insert_day(week_deltas, empty_today, ctx.config.week_start);
+}
+
+/// Holidays are shown in the weeks they fall in, even when nothing was logged on them.
+/// No weeks are added for them, as they would only ever hold the holiday itself
+fn insert_holidays(week_deltas: &mut Vec<WeekDelta>, ctx: &EvalContext) {
+    for holiday in &ctx.config.holidays {
+        let in_shown_week = week_deltas.iter()
+            .any(|week| week.starting_date <= holiday.date && holiday.date < week.starting_date + TimeDelta::days(7));
 
-    let week_deltas = weeks.into_iter()
-        .map(eval_week)
-        .collect::<Vec<_>>();
+        let logged = week_deltas.iter()
+            .flat_map(|week| &week.day_deltas)
+            .any(|day| day.date == holiday.date);
+
+        if in_shown_week && !logged {
+            let day_delta = DayDelta {
+                date: holiday.date,
+                expected: TimeDelta::zero(),
+                actual: TimeDelta::zero(),
+                delta: TimeDelta::zero(),
+                had_lunch: false,
+                logged: false,
+                has_future_time: false,
+                working_day: None,
+                absence: Absence::default(),
+                holiday: Some(holiday.name.clone()),
+                log_deltas: vec![],
+            };
+
+            insert_day(week_deltas, day_delta, ctx.config.week_start);
+        }
+    }
+}
+
+/// Reports every problem found across all weeks, rather than stopping at the first
+pub fn evaluate_timesheets(timesheets: Timesheets, ctx: &EvalContext) -> Result<TotalDelta, Vec<EvalError>> {
+    let Timesheets { balance_forward, contracts, allowances, weeks, .. } = timesheets;
+    let today = ctx.today();
+
+    let mut errors = vec![];
+    let mut evaluated = vec![];
+
+    let contracts = eval_contracts(contracts)
+        .unwrap_or_else(|mut contract_errors| {
+            errors.append(&mut contract_errors);
+            vec![]
+        });
+
+    // Contracts in the timesheet take precedence over any starting on the same date in the config
+    let mut config = ctx.config.clone();
+
+    for (from, schedule) in contracts {
+        config.set_contract(from, schedule);
+    }
+
+    let ctx = &EvalContext { config, ..ctx.clone() };
+
+    let allowances = eval_allowances(allowances)
+        .unwrap_or_else(|mut allowance_errors| {
+            errors.append(&mut allowance_errors);
+            vec![]
+        });
+
+    for Spanned { node: week, spans: week_spans } in weeks {
+        match eval_week(week, &week_spans, ctx) {
+            Ok(week_delta) => evaluated.push(week_delta),
+            Err(mut week_errors) => errors.append(&mut week_errors),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let (mut week_deltas, carried): (Vec<_>, Vec<_>) = evaluated.into_iter()
+        .unzip();
+
+    let today_logged = week_deltas.iter()
+        .flat_map(|w| &w.day_deltas)
+        .any(|day| day.date == today);
+
+    if !today_logged {
+        insert_empty_today(&mut week_deltas, ctx);
+    }
+
+    for (date, delta) in carried.into_iter().flatten() {
+        if !delta.is_zero() {
+            carry_past_midnight(&mut week_deltas, date, delta, ctx);
+        }
+    }
+
+    insert_holidays(&mut week_deltas, ctx);
+
+    let balance_forward = balance_forward.map(|balance_forward| balance_forward.node.evaluate());
 
     let total_delta = week_deltas.iter()
-        .map(|w| w.week_delta)
-        .sum();
+        .map(WeekDelta::total_delta)
+        .chain(balance_forward.as_ref().map(|balance| balance.delta))
+        .sum::<TimeDelta>();
 
-    // TODO add check to make sure this is actually today.
-    // Currently assumes that today is already in the process of being logged
-    let today_delta = week_deltas.last()
-        .and_then(|w| w.day_deltas.last())
+    // Days after today count for nothing as it is, but are left out all the same
+    let from_today_delta = week_deltas.iter()
+        .flat_map(|w| &w.day_deltas)
+        .filter(|day| today <= day.date)
         .map(|day| day.delta)
-        .unwrap_or_else(TimeDelta::zero);
+        .sum::<TimeDelta>();
 
-    TotalDelta {
-        total_delta, 
-        total_delta_excluding_today: total_delta - today_delta,
+    Ok(TotalDelta {
+        total_delta,
+        balance_forward,
+        total_delta_excluding_today: total_delta - from_today_delta,
         week_deltas,
+        allowances,
+        today,
+    })
+}
+
+#[test]
+fn check_contracts_only_apply_from_their_date() {
+    use crate::evaluator::context::evaluate_str;
+
+    let code = "CONTRACT FROM 2024-09-23\n  Monday 6h\n  Tuesday 6h\n  Wednesday 6h\n  Thursday 6h\n\nWEEK 2024-09-16\n  Friday\n    WORKING DAY 09:00 - 17:00\n\nWEEK 2024-09-23\n  Monday\n    WORKING DAY 09:00 - 15:00\n\n  Friday\n    WORKING DAY 09:00 - 10:00\n";
+
+    let ctx = EvalContext::at_utc("2024-09-27 18:00");
+    let total_delta = evaluate_str(code, &ctx);
+
+    let expected: Vec<_> = total_delta.week_deltas.iter()
+        .flat_map(|week| &week.day_deltas)
+        .map(|day| (day.expected.num_hours(), day.delta.num_hours()))
+        .collect();
+
+    assert_eq!(expected, [(8, 0), (6, 0), (0, 1)]);
+}
+
+#[test]
+fn check_days_still_to_come_count_for_nothing() {
+    use crate::evaluator::context::evaluate_str;
+
+    let code = "WEEK 2026-10-12\n  Friday\n    WORKING DAY 09:00 - 17:30\n\nWEEK 2026-10-19\n  Tuesday\n    WORKING DAY 09:00 - 17:00\n";
+
+    let ctx = EvalContext::at_utc("2026-10-18 12:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let tuesday = total_delta.day(NaiveDate::from_ymd_opt(2026, 10, 20).unwrap()).unwrap();
+
+    assert_eq!((tuesday.expected, tuesday.delta, tuesday.has_future_time), (TimeDelta::hours(8), TimeDelta::zero(), true));
+    assert_eq!(total_delta.total_delta_excluding_today, TimeDelta::minutes(30));
+    assert_eq!(total_delta.total_delta, TimeDelta::minutes(30));
+}
+
+#[test]
+fn check_absence_is_credited_apart_from_toil() {
+    use crate::evaluator::context::evaluate_str;
+
+    let code = "WEEK 2026-10-12\n  Monday\n    LEAVE 1d\n\n  Tuesday\n    SICK | Flu\n\n  Wednesday\n    HOLIDAY\n\n  Thursday\n    WORKING DAY 09:00 - 13:00\n    TOIL 13:00 - 17:00\n\n  Friday\n    WORKING DAY 09:00 - 17:00\n\nWEEK 2026-10-19\n  Monday\n    LEAVE 1d\n\n  Tuesday\n    SICK\n\n  Wednesday\n    HOLIDAY\n\n  Thursday\n    TOIL\n";
+
+    let ctx = EvalContext::at_utc("2026-10-16 18:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let day = |day: u32| total_delta.day(NaiveDate::from_ymd_opt(2026, 10, day).unwrap()).unwrap();
+
+    assert_eq!((day(12).delta, day(12).absence.leave), (TimeDelta::zero(), TimeDelta::hours(8)));
+    assert_eq!((day(13).delta, day(13).absence.sick), (TimeDelta::zero(), TimeDelta::hours(8)));
+    assert_eq!((day(14).delta, day(14).absence.holiday), (TimeDelta::zero(), TimeDelta::hours(8)));
+
+    // TOIL is drawn from the balance, leaving the day short of its expected hours
+    assert_eq!((day(15).delta, day(15).absence.toil), (TimeDelta::hours(-4), TimeDelta::hours(4)));
+    assert_eq!(total_delta.total_delta, TimeDelta::hours(-4));
+
+    // Absence still to come is only booked, so isn't counted in the totals
+    assert_eq!((day(22).delta, day(22).absence.toil), (TimeDelta::zero(), TimeDelta::hours(8)));
+    assert_eq!(total_delta.absence(), Absence {
+        leave: TimeDelta::hours(8),
+        sick: TimeDelta::hours(8),
+        holiday: TimeDelta::hours(8),
+        toil: TimeDelta::hours(4),
+    });
+}
+
+#[test]
+fn check_time_past_midnight_is_logged_on_a_day_of_its_own() {
+    use crate::{config::{Config, Overnight}, evaluator::context::evaluate_str};
+
+    let code = "WEEK 2026-10-05\n  Saturday\n    WORK 22:00 - 02:00 | Release\n";
+
+    let ctx = EvalContext { config: Config { overnight: Overnight::Split, ..Config::default() }, ..EvalContext::at_utc("2026-10-16 12:00") };
+
+    let total_delta = evaluate_str(code, &ctx);
+    let sunday = total_delta.day(NaiveDate::from_ymd_opt(2026, 10, 11).unwrap()).unwrap();
+
+    let log_deltas: Vec<_> = sunday.log_deltas.iter()
+        .map(|log_delta| (log_delta.event.as_str(), log_delta.delta))
+        .collect();
+
+    assert_eq!(sunday.delta, TimeDelta::hours(2));
+    assert_eq!(log_deltas, [("Continued past midnight", TimeDelta::hours(2))]);
+}
+
+#[test]
+fn check_days_are_measured_against_the_hours_expected() {
+    use crate::evaluator::context::evaluate_str;
+
+    let code = "CONTRACT FROM 2026-10-05\n  Monday 8h\n  Thursday 6h\n  Friday 4h\n\nWEEK 2026-10-05\n  Monday\n    LEAVE HALF AM\n    WORKING DAY 13:00 - 17:00\n\n  Thursday\n    LEAVE 0.5d\n    WORKING DAY 09:00 - 12:00\n\n  Friday\n    LEAVE 1d\n";
+
+    let ctx = EvalContext::at_utc("2026-10-16 12:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let day = |day: u32| total_delta.day(NaiveDate::from_ymd_opt(2026, 10, day).unwrap()).unwrap();
+
+    assert_eq!((day(5).delta, day(5).absence.leave), (TimeDelta::zero(), TimeDelta::hours(4)));
+    assert_eq!((day(8).delta, day(8).absence.leave), (TimeDelta::zero(), TimeDelta::hours(3)));
+    assert_eq!((day(9).delta, day(9).absence.leave), (TimeDelta::zero(), TimeDelta::hours(4)));
+}
+
+#[test]
+fn check_now_only_runs_overnight_from_a_later_start() {
+    use crate::evaluator::context::evaluate_str;
+
+    let ctx = EvalContext::at_utc("2024-09-24 07:00");
+
+    // Started yesterday morning, so it isn't running overnight but left open
+    let morning = parse_timesheets("WEEK 2024-09-23\n  Monday\n    WORKING DAY 09:00 - NOW\n").unwrap();
+    let errors = evaluate_timesheets(morning, &ctx).err();
+
+    assert!(matches!(errors.as_deref(), Some([EvalError::NowOutsideToday { .. }])));
+
+    let total_delta = evaluate_str("WEEK 2024-09-23\n  Monday\n    WORKING DAY 22:00 - NOW\n", &ctx);
+    let monday = total_delta.day(NaiveDate::from_ymd_opt(2024, 9, 23).unwrap()).unwrap();
+
+    assert_eq!(monday.delta, TimeDelta::hours(1));
+}
+
+#[test]
+fn check_time_past_midnight_goes_to_the_start_day_by_default() {
+    use crate::evaluator::context::evaluate_str;
+
+    let ctx = EvalContext::at_utc("2024-09-25 12:00");
+
+    // An end earlier than the start rolls over into the next day, just as `+1` does
+    for range in ["22:00 - 06:00", "22:00 - 06:00+1"] {
+        let total_delta = evaluate_str(&format!("WEEK 2024-09-23\n  Monday\n    WORKING DAY {range}\n"), &ctx);
+        let monday = total_delta.day(NaiveDate::from_ymd_opt(2024, 9, 23).unwrap()).unwrap();
+
+        assert_eq!((monday.actual, monday.delta), (TimeDelta::hours(8), TimeDelta::zero()), "{range}");
+        assert!(total_delta.day(NaiveDate::from_ymd_opt(2024, 9, 24).unwrap()).is_none(), "{range}");
     }
 }
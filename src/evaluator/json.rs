@@ -0,0 +1,421 @@
+/*
+ * Machine-readable output, for status bars and dashboards.
+ * The shape is described in the README, and any change to it that could break a consumer
+ * (removing or renaming a field, or changing its meaning) must bump SCHEMA_VERSION.
+ * Durations are whole minutes, dates are ISO 8601 and times are RFC 3339
+ */
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeDelta};
+use serde::Serialize;
+
+use crate::{evaluator::{deadline::Deadlines, eval::{Absence, BalanceForwardDelta, DayDelta, LogDelta, WeekDelta}, leave::{CarriedOver, LeaveLedger, LeaveYear}, DayReport, Report}, parser::DayName};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct ReportJson {
+    schema_version: u32,
+    generated_at: String,
+    today: String,
+    total_delta_minutes: i64,
+    total_delta_excluding_today_minutes: i64,
+    balance_forward: Option<BalanceForwardJson>,
+    absence: AbsenceJson,
+    weeks: Vec<WeekJson>,
+    deadlines: DeadlinesJson,
+}
+
+#[derive(Serialize)]
+struct DayReportJson {
+    schema_version: u32,
+    day: DayJson,
+}
+
+#[derive(Serialize)]
+struct WeekJson {
+    starting_date: String,
+    delta_minutes: i64,
+    balance_forward: Option<BalanceForwardJson>,
+    days: Vec<DayJson>,
+}
+
+#[derive(Serialize)]
+struct BalanceForwardJson {
+    minutes: i64,
+    summary: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DayJson {
+    date: String,
+    weekday: String,
+    expected_minutes: i64,
+    actual_minutes: i64,
+    delta_minutes: i64,
+    had_lunch: bool,
+    logged: bool,
+    has_future_time: bool,
+    absence: AbsenceJson,
+    holiday: Option<String>,
+    logs: Vec<LogJson>,
+}
+
+#[derive(Serialize)]
+struct AbsenceJson {
+    leave_minutes: i64,
+    sick_minutes: i64,
+    holiday_minutes: i64,
+    toil_minutes: i64,
+}
+
+#[derive(Serialize)]
+struct LogJson {
+    event: String,
+    summary: Option<String>,
+    minutes: i64,
+}
+
+#[derive(Serialize)]
+struct LeaveReportJson {
+    schema_version: u32,
+    today: String,
+    years: Vec<LeaveYearJson>,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct LeaveYearJson {
+    start: String,
+    end: String,
+    summary: Option<String>,
+    allowance_days: f64,
+    carried_over: Option<CarriedOverJson>,
+    taken_days: f64,
+    booked_days: f64,
+    remaining_days: f64,
+}
+
+#[derive(Serialize)]
+struct CarriedOverJson {
+    days: f64,
+    expires: Option<String>,
+    unused_days: f64,
+}
+
+#[derive(Serialize)]
+struct DeadlinesJson {
+    not_started: bool,
+    earliest_finish: String,
+    earliest_finish_with_lunch: Option<String>,
+    earliest_finish_passed: bool,
+    retain_credit: String,
+    retain_credit_with_lunch: Option<String>,
+    retain_credit_passed: bool,
+}
+
+fn minutes(delta: &TimeDelta) -> i64 {
+    delta.num_minutes()
+}
+
+/// e.g. `2024-09-23`, as dates are written in both JSON and tables
+pub(super) fn date(date: &NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn time(time: DateTime<FixedOffset>) -> String {
+    time.to_rfc3339()
+}
+
+impl From<&LogDelta> for LogJson {
+    fn from(LogDelta { event, summary, delta }: &LogDelta) -> Self {
+        LogJson {
+            event: event.clone(),
+            summary: summary.clone(),
+            minutes: minutes(delta),
+        }
+    }
+}
+
+impl From<Absence> for AbsenceJson {
+    fn from(Absence { leave, sick, holiday, toil }: Absence) -> Self {
+        AbsenceJson {
+            leave_minutes: minutes(&leave),
+            sick_minutes: minutes(&sick),
+            holiday_minutes: minutes(&holiday),
+            toil_minutes: minutes(&toil),
+        }
+    }
+}
+
+impl From<&DayDelta> for DayJson {
+    fn from(day_delta: &DayDelta) -> Self {
+        let DayDelta { date: day_date, expected, actual, delta, had_lunch, logged, has_future_time, absence, holiday, log_deltas, .. } = day_delta;
+
+        DayJson {
+            date: date(day_date),
+            weekday: DayName(day_date.weekday()).to_string(),
+            expected_minutes: minutes(expected),
+            actual_minutes: minutes(actual),
+            delta_minutes: minutes(delta),
+            had_lunch: *had_lunch,
+            logged: *logged,
+            has_future_time: *has_future_time,
+            absence: AbsenceJson::from(*absence),
+            holiday: holiday.clone(),
+            logs: log_deltas.iter().map(LogJson::from).collect(),
+        }
+    }
+}
+
+impl From<&BalanceForwardDelta> for BalanceForwardJson {
+    fn from(BalanceForwardDelta { delta, summary }: &BalanceForwardDelta) -> Self {
+        BalanceForwardJson {
+            minutes: minutes(delta),
+            summary: summary.clone(),
+        }
+    }
+}
+
+impl From<&WeekDelta> for WeekJson {
+    fn from(WeekDelta { starting_date, week_delta, balance_forward, day_deltas }: &WeekDelta) -> Self {
+        WeekJson {
+            starting_date: date(starting_date),
+            delta_minutes: minutes(week_delta),
+            balance_forward: balance_forward.as_ref().map(BalanceForwardJson::from),
+            days: day_deltas.iter().map(DayJson::from).collect(),
+        }
+    }
+}
+
+/// Days are given to two decimal places, as that's as precise as they're shown anywhere else
+fn days(days: f64) -> f64 {
+    (days * 100.0).round() / 100.0
+}
+
+impl From<&LeaveYear> for LeaveYearJson {
+    fn from(year: &LeaveYear) -> Self {
+        let LeaveYear { start, end, allowance, carried_over, taken, booked, summary } = year;
+
+        LeaveYearJson {
+            start: date(start),
+            end: date(end),
+            summary: summary.clone(),
+            allowance_days: days(*allowance),
+            carried_over: carried_over.as_ref().map(|CarriedOver { days: carried, expires, unused }| CarriedOverJson {
+                days: days(*carried),
+                expires: expires.as_ref().map(date),
+                unused_days: days(*unused),
+            }),
+            taken_days: days(*taken),
+            booked_days: days(*booked),
+            remaining_days: days(year.remaining()),
+        }
+    }
+}
+
+impl From<&Deadlines> for DeadlinesJson {
+    fn from(deadlines: &Deadlines) -> Self {
+        DeadlinesJson {
+            not_started: deadlines.not_started,
+            earliest_finish: time(deadlines.earliest_finish),
+            earliest_finish_with_lunch: deadlines.with_lunch(deadlines.earliest_finish).map(time),
+            earliest_finish_passed: deadlines.has_passed(deadlines.earliest_finish),
+            retain_credit: time(deadlines.retain_credit),
+            retain_credit_with_lunch: deadlines.with_lunch(deadlines.retain_credit).map(time),
+            retain_credit_passed: deadlines.has_passed(deadlines.retain_credit),
+        }
+    }
+}
+
+impl Report<'_> {
+    /// Every week, unlike the boxes, which only show the last few
+    pub fn to_json(&self) -> String {
+        let Report { total_delta, ctx, .. } = self;
+
+        let report = ReportJson {
+            schema_version: SCHEMA_VERSION,
+            generated_at: time(ctx.now),
+            today: date(&total_delta.today),
+            total_delta_minutes: minutes(&total_delta.total_delta),
+            total_delta_excluding_today_minutes: minutes(&total_delta.total_delta_excluding_today),
+            balance_forward: total_delta.balance_forward.as_ref().map(BalanceForwardJson::from),
+            absence: AbsenceJson::from(total_delta.absence()),
+            weeks: total_delta.week_deltas.iter().map(WeekJson::from).collect(),
+            deadlines: DeadlinesJson::from(&Deadlines::new(total_delta, ctx)),
+        };
+
+        serde_json::to_string_pretty(&report)
+            .expect("The report only holds strings, numbers and booleans")
+    }
+}
+
+impl DayReport<'_> {
+    pub fn to_json(&self) -> String {
+        let DayReport(day_delta) = self;
+
+        let report = DayReportJson {
+            schema_version: SCHEMA_VERSION,
+            day: DayJson::from(*day_delta),
+        };
+
+        serde_json::to_string_pretty(&report)
+            .expect("The report only holds strings, numbers and booleans")
+    }
+}
+
+impl LeaveLedger {
+    pub fn to_json(&self) -> String {
+        let report = LeaveReportJson {
+            schema_version: SCHEMA_VERSION,
+            today: date(&self.today),
+            years: self.years.iter().map(LeaveYearJson::from).collect(),
+            warnings: self.warnings.iter().map(ToString::to_string).collect(),
+        };
+
+        serde_json::to_string_pretty(&report)
+            .expect("The report only holds strings, numbers and booleans")
+    }
+}
+
+#[test]
+fn check_schema_is_pinned() {
+    use crate::evaluator::{context::evaluate_str, EvalContext, Window};
+
+    let code = "BALANCE FORWARD +1h | from 2025\n\nALLOWANCE 25d FROM 2026-01-01 CARRY OVER 2d UNTIL 2026-03-31\n\nWEEK 2026-10-12\n  Thursday\n    WORKING DAY 09:00 - 17:30\n    LUNCH 30m\n\n  Friday\n    LEAVE HALF AM\n    WORKING DAY 13:00 - NOW\n";
+
+    let ctx = EvalContext::at_utc("2026-10-16 15:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let report = Report { total_delta: &total_delta, ctx: &ctx, window: Window::default() };
+
+    // A change to either of these that could break a consumer must bump SCHEMA_VERSION
+    assert_eq!(report.to_json(), r#"{
+  "schema_version": 1,
+  "generated_at": "2026-10-16T15:00:00+00:00",
+  "today": "2026-10-16",
+  "total_delta_minutes": -60,
+  "total_delta_excluding_today_minutes": 60,
+  "balance_forward": {
+    "minutes": 60,
+    "summary": "from 2025"
+  },
+  "absence": {
+    "leave_minutes": 240,
+    "sick_minutes": 0,
+    "holiday_minutes": 0,
+    "toil_minutes": 0
+  },
+  "weeks": [
+    {
+      "starting_date": "2026-10-12",
+      "delta_minutes": -120,
+      "balance_forward": null,
+      "days": [
+        {
+          "date": "2026-10-15",
+          "weekday": "Thursday",
+          "expected_minutes": 480,
+          "actual_minutes": 480,
+          "delta_minutes": 0,
+          "had_lunch": true,
+          "logged": true,
+          "has_future_time": false,
+          "absence": {
+            "leave_minutes": 0,
+            "sick_minutes": 0,
+            "holiday_minutes": 0,
+            "toil_minutes": 0
+          },
+          "holiday": null,
+          "logs": [
+            {
+              "event": "WORKING DAY 09:00 - 17:30",
+              "summary": null,
+              "minutes": 510
+            },
+            {
+              "event": "LUNCH 30m",
+              "summary": null,
+              "minutes": -30
+            }
+          ]
+        },
+        {
+          "date": "2026-10-16",
+          "weekday": "Friday",
+          "expected_minutes": 480,
+          "actual_minutes": 360,
+          "delta_minutes": -120,
+          "had_lunch": false,
+          "logged": true,
+          "has_future_time": false,
+          "absence": {
+            "leave_minutes": 240,
+            "sick_minutes": 0,
+            "holiday_minutes": 0,
+            "toil_minutes": 0
+          },
+          "holiday": null,
+          "logs": [
+            {
+              "event": "LEAVE HALF AM",
+              "summary": null,
+              "minutes": 240
+            },
+            {
+              "event": "WORKING DAY 13:00 - NOW",
+              "summary": null,
+              "minutes": 120
+            }
+          ]
+        }
+      ]
+    }
+  ],
+  "deadlines": {
+    "not_started": false,
+    "earliest_finish": "2026-10-16T16:00:00+00:00",
+    "earliest_finish_with_lunch": "2026-10-16T16:30:00+00:00",
+    "earliest_finish_passed": false,
+    "retain_credit": "2026-10-16T17:00:00+00:00",
+    "retain_credit_with_lunch": "2026-10-16T17:30:00+00:00",
+    "retain_credit_passed": false
+  }
+}"#);
+
+    assert_eq!(LeaveLedger::new(&total_delta).to_json(), r#"{
+  "schema_version": 1,
+  "today": "2026-10-16",
+  "years": [
+    {
+      "start": "2026-01-01",
+      "end": "2026-12-31",
+      "summary": null,
+      "allowance_days": 25.0,
+      "carried_over": {
+        "days": 2.0,
+        "expires": "2026-03-31",
+        "unused_days": 2.0
+      },
+      "taken_days": 0.5,
+      "booked_days": 0.0,
+      "remaining_days": 24.5
+    }
+  ],
+  "warnings": [
+    "2d carried over expired unused on 31st March 2026"
+  ]
+}"#);
+}
+
+#[test]
+fn check_days_still_to_come_are_not_worked() {
+    use crate::evaluator::{context::evaluate_str, EvalContext};
+
+    let code = "WEEK 2024-09-23\n  Wednesday\n    WORKING DAY 09:00 - 17:00\n";
+
+    let ctx = EvalContext::at_utc("2024-09-24 12:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let wednesday = DayJson::from(total_delta.day(NaiveDate::from_ymd_opt(2024, 9, 25).unwrap()).unwrap());
+
+    assert_eq!((wednesday.expected_minutes, wednesday.actual_minutes, wednesday.delta_minutes), (480, 0, 0));
+}
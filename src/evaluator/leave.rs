@@ -0,0 +1,179 @@
+/*
+ * Leave is tracked in days against each year's ALLOWANCE, with each day's LEAVE measured against the hours expected of it.
+ * Leave on days up to and including today counts as taken, while leave on later days counts as booked.
+ * Any days carried over are used up first, so that as little as possible is lost when they expire
+ */
+
+use chrono::{Months, NaiveDate, TimeDelta};
+use thiserror::Error;
+
+use crate::{evaluator::eval::{AllowanceDelta, CarryOverDelta, DayDelta, TotalDelta}, parser::LongDate};
+
+/// Leeway for sums of fractional days, which needn't add up exactly
+const EPSILON: f64 = 0.005;
+
+/// e.g. `2.5`, to at most two decimal places and without a unit
+pub(super) fn days(days: f64) -> String {
+    let days = format!("{days:.2}");
+
+    days.trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_owned()
+}
+
+/// e.g. `2.5d`
+pub fn days_to_string(count: f64) -> String {
+    format!("{}d", days(count))
+}
+
+pub struct CarriedOver {
+    pub days: f64,
+    pub expires: Option<NaiveDate>,
+    /// Not used by the time it expires, even counting leave booked ahead of then
+    pub unused: f64,
+}
+
+pub struct LeaveYear {
+    pub start: NaiveDate,
+    /// The last day of the year, which is the day before the next allowance starts, or a year on
+    pub end: NaiveDate,
+    pub allowance: f64,
+    pub carried_over: Option<CarriedOver>,
+    pub taken: f64,
+    pub booked: f64,
+    pub summary: Option<String>,
+}
+
+impl LeaveYear {
+    /// What can still be booked, leaving out days carried over that will be lost
+    pub fn remaining(&self) -> f64 {
+        let carried_over = self.carried_over.as_ref()
+            .map_or(0.0, |carried_over| carried_over.days - carried_over.unused);
+
+        self.allowance + carried_over - self.taken - self.booked
+    }
+
+    pub fn carry_over_has_expired(&self, today: NaiveDate) -> bool {
+        self.carried_over.as_ref()
+            .and_then(|carried_over| carried_over.expires)
+            .is_some_and(|expires| expires < today)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LeaveWarning {
+    #[error("Leave from {date} onwards goes {over} past the {available} available in the leave year from {start}",
+        date = LongDate::from(*.date), over = days_to_string(*.over), available = days_to_string(*.available), start = LongDate::from(*.start))]
+    OverBooked { start: NaiveDate, date: NaiveDate, over: f64, available: f64 },
+
+    #[error("{days} carried over expired unused on {expires}", days = days_to_string(*.days), expires = LongDate::from(*.expires))]
+    CarryOverExpired { days: f64, expires: NaiveDate },
+
+    #[error("{days} carried over will expire on {expires}, unless more leave is booked before then", days = days_to_string(*.days), expires = LongDate::from(*.expires))]
+    CarryOverExpiring { days: f64, expires: NaiveDate },
+}
+
+/// Each year's leave, with warnings for leave booked beyond what's available and for days carried over going to waste
+pub struct LeaveLedger {
+    pub years: Vec<LeaveYear>,
+    pub warnings: Vec<LeaveWarning>,
+    pub today: NaiveDate,
+}
+
+/// The LEAVE taken on the day, as a fraction of the hours expected of it.
+/// Days with nothing expected of them have no leave to take
+fn leave_days(day: &DayDelta) -> f64 {
+    match day.expected.is_zero() {
+        true => 0.0,
+        false => day.absence.leave.num_seconds() as f64 / day.expected.num_seconds() as f64,
+    }
+}
+
+impl LeaveLedger {
+    pub fn new(total_delta: &TotalDelta) -> Self {
+        let TotalDelta { allowances, week_deltas, today, .. } = total_delta;
+        let today = *today;
+
+        let mut years = vec![];
+        let mut warnings = vec![];
+
+        for (i, AllowanceDelta { start, days, carry_over, summary }) in allowances.iter().enumerate() {
+            let end = allowances.get(i + 1)
+                .map(|next| next.start)
+                .or_else(|| start.checked_add_months(Months::new(12)))
+                .map_or(NaiveDate::MAX, |next| next - TimeDelta::days(1));
+
+            let leave: Vec<_> = week_deltas.iter()
+                .flat_map(|week| &week.day_deltas)
+                .filter(|day| *start <= day.date && day.date <= end)
+                .map(|day| (day.date, leave_days(day)))
+                .filter(|(_, days)| EPSILON < *days)
+                .collect();
+
+            let used_by = |date: NaiveDate| leave.iter()
+                .filter(|(day, _)| *day <= date)
+                .fold(0.0, |used, (_, days)| used + days);
+
+            let carried_over = carry_over.as_ref()
+                .map(|CarryOverDelta { days, expires }| CarriedOver {
+                    days: *days,
+                    expires: *expires,
+                    unused: expires.map_or(0.0, |expires| (days - used_by(expires)).max(0.0)),
+                });
+
+            let year = LeaveYear {
+                start: *start,
+                end,
+                allowance: *days,
+                carried_over,
+                taken: used_by(today),
+                booked: used_by(end) - used_by(today),
+                summary: summary.clone(),
+            };
+
+            if let Some(CarriedOver { unused, expires: Some(expires), .. }) = &year.carried_over {
+                if EPSILON < *unused {
+                    warnings.push(match *expires < today {
+                        true => LeaveWarning::CarryOverExpired { days: *unused, expires: *expires },
+                        false => LeaveWarning::CarryOverExpiring { days: *unused, expires: *expires },
+                    });
+                }
+            }
+
+            let available = year.remaining() + year.taken + year.booked;
+
+            // The first day to take the leave past what's available
+            let over_from = leave.iter()
+                .find(|(date, _)| available + EPSILON < used_by(*date));
+
+            if let Some((date, _)) = over_from {
+                warnings.push(LeaveWarning::OverBooked {
+                    start: *start,
+                    date: *date,
+                    over: -year.remaining(),
+                    available,
+                });
+            }
+
+            years.push(year);
+        }
+
+        LeaveLedger { years, warnings, today }
+    }
+}
+
+#[test]
+fn check_carry_over_is_used_first() {
+    use crate::evaluator::{context::evaluate_str, EvalContext};
+
+    let code = "ALLOWANCE 20d FROM 1st January 2025 CARRY OVER 3d UNTIL 31st March 2025\n\nWEEK 2025-03-24\n  Monday\n    LEAVE 1d\n\n  Tuesday\n    LEAVE HALF AM\n    WORKING DAY 13:00 - 17:00\n\n  Friday\n    LEAVE 1d\n";
+
+    let ctx = EvalContext::at_utc("2025-03-26 12:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let LeaveLedger { years, warnings, .. } = LeaveLedger::new(&total_delta);
+
+    assert_eq!((years[0].taken, years[0].booked), (1.5, 1.0));
+    assert_eq!(years[0].carried_over.as_ref().map(|carried_over| carried_over.unused), Some(0.5));
+    assert_eq!(years[0].remaining(), 20.0);
+    assert_eq!(warnings.iter().map(ToString::to_string).collect::<Vec<_>>(), ["0.5d carried over will expire on 31st March 2025, unless more leave is booked before then"]);
+}
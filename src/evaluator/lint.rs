@@ -0,0 +1,272 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use thiserror::Error;
+
+use crate::{evaluator::EvalContext, parser::*, utils::diagnostic::{Diagnostic, Severity}};
+
+/// Mistakes that the grammar and evaluation both accept, but that are almost certainly not what was meant
+#[derive(Error, Debug)]
+pub enum Lint {
+    #[error("{0} is listed after {1}, which comes later in the week")]
+    DayOutOfOrder(DayName, DayName, Span),
+
+    #[error("{day}: '{log}' ends before it starts, so runs past midnight")]
+    BackwardsRange { day: String, log: String, span: Span },
+
+    #[error("{day}: '{log}' overlaps '{other}' on line {line}")]
+    OverlappingWorkingDays { day: String, log: String, other: String, line: usize, span: Span },
+
+    #[error("{day}: '{log}' overlaps '{other}' on line {line}")]
+    OverlappingBreaks { day: String, log: String, other: String, line: usize, span: Span },
+
+    #[error("{day}: '{log}' falls outside of the time worked")]
+    BreakOutsideWorkingDay { day: String, log: String, span: Span },
+
+    #[error("{day}: LUNCH is logged on a day off, with no work logged")]
+    LunchOnLeaveDay { day: String, span: Span },
+}
+
+impl Lint {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::DayOutOfOrder(..)
+            | Lint::BackwardsRange { .. } => Severity::Warning,
+
+            Lint::OverlappingWorkingDays { .. }
+            | Lint::OverlappingBreaks { .. }
+            | Lint::BreakOutsideWorkingDay { .. }
+            | Lint::LunchOnLeaveDay { .. } => Severity::Error,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Lint::DayOutOfOrder(_, _, span)
+            | Lint::BackwardsRange { span, .. }
+            | Lint::OverlappingWorkingDays { span, .. }
+            | Lint::OverlappingBreaks { span, .. }
+            | Lint::BreakOutsideWorkingDay { span, .. }
+            | Lint::LunchOnLeaveDay { span, .. } => *span,
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Lint::DayOutOfOrder(..) =>
+                Some("list days in the order they fall within their week".into()),
+
+            Lint::BackwardsRange { .. } =>
+                Some("if that's intended, mark the end as being on the next day, e.g. '22:00 - 06:00+1'".into()),
+
+            Lint::OverlappingWorkingDays { .. } =>
+                Some("the time they share is counted twice, so merge them into a single range".into()),
+
+            Lint::OverlappingBreaks { .. } =>
+                Some("the time they share is taken off twice, so merge them into a single range".into()),
+
+            Lint::BreakOutsideWorkingDay { .. } =>
+                Some("breaks are taken off time worked, so should fall within a WORKING DAY or WORK range".into()),
+
+            Lint::LunchOnLeaveDay { .. } =>
+                Some("remove the LUNCH, as no work was logged for it to be taken from".into()),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            severity: self.severity(),
+            message: self.to_string(),
+            span: self.span(),
+            hint: self.hint(),
+        }
+    }
+}
+
+/// A log given as a range of times, resolved to when it started and ended
+struct Ranged<'a> {
+    event: &'a LogEvent,
+    spans: &'a LogSpans,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+impl Ranged<'_> {
+    fn overlaps(&self, other: &Ranged<'_>) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn is_within(&self, other: &Ranged<'_>) -> bool {
+        other.start <= self.start && self.end <= other.end
+    }
+}
+
+/// Each later range overlapping an earlier one, along with the earlier one
+fn overlapping<'r, 'a>(ranged: &'r [&'r Ranged<'a>]) -> impl Iterator<Item = (&'r Ranged<'a>, &'r Ranged<'a>)> {
+    ranged.iter()
+        .enumerate()
+        .flat_map(move |(i, later)| ranged[..i].iter()
+            .filter(|earlier| earlier.overlaps(later))
+            .map(|earlier| (*later, *earlier)))
+}
+
+fn lint_day(Day(day_name, logs): &Day, date: NaiveDate, spans: &DaySpans, ctx: &EvalContext) -> Vec<Lint> {
+    let day = format!("{day_name} {}", LongDate::from(date));
+    let mut lints = vec![];
+
+    let logs: Vec<_> = logs.iter()
+        .zip(&spans.logs)
+        .map(|(Log(event), spans)| (event, spans))
+        .collect();
+
+    for (event, spans) in &logs {
+        if let Some(TimePeriod::TimeRange(TimeRange(Time(start), TimeRangeEnd::Time(Time(end))))) = event.time_period() {
+            if end < start {
+                lints.push(Lint::BackwardsRange { day: day.clone(), log: event.to_string(), span: spans.time_period });
+            }
+        }
+    }
+
+    // Empty ranges, and NOW where it isn't allowed, are already reported by evaluation
+    let ranged: Vec<_> = logs.iter()
+        .filter_map(|(event, spans)| match event.time_period() {
+            Some(TimePeriod::TimeRange(range)) => {
+                let (start, end) = range.resolve(date, ctx);
+                Some(Ranged { event, spans, start, end })
+            },
+
+            Some(TimePeriod::Period(_)) | None => None,
+        })
+        .filter(|ranged| ranged.start < ranged.end)
+        .collect();
+
+    let of_kind = |matches: fn(&LogEvent) -> bool| -> Vec<_> {
+        ranged.iter()
+            .filter(|ranged| matches(ranged.event))
+            .collect()
+    };
+
+    let working_days = of_kind(|event| matches!(event, LogEvent::WorkingDay(_)));
+    let worked = of_kind(|event| matches!(event, LogEvent::WorkingDay(_) | LogEvent::Work(_)));
+    let breaks = of_kind(|event| matches!(event, LogEvent::Break(_) | LogEvent::Lunch(_)));
+
+    for (later, earlier) in overlapping(&working_days) {
+        lints.push(Lint::OverlappingWorkingDays {
+            day: day.clone(),
+            log: later.event.to_string(),
+            other: earlier.event.to_string(),
+            line: earlier.spans.span.line,
+            span: later.spans.time_period,
+        });
+    }
+
+    for (later, earlier) in overlapping(&breaks) {
+        lints.push(Lint::OverlappingBreaks {
+            day: day.clone(),
+            log: later.event.to_string(),
+            other: earlier.event.to_string(),
+            line: earlier.spans.span.line,
+            span: later.spans.time_period,
+        });
+    }
+
+    // Without any ranges of work, there's nothing to say when the breaks should have been
+    if !worked.is_empty() {
+        for outside in breaks.iter().filter(|taken| !worked.iter().any(|work| taken.is_within(work))) {
+            lints.push(Lint::BreakOutsideWorkingDay { day: day.clone(), log: outside.event.to_string(), span: outside.spans.time_period });
+        }
+    }
+
+    let has = |matches: fn(&LogEvent) -> bool| logs.iter().any(|(event, _)| matches(event));
+
+    if has(|event| matches!(event, LogEvent::Leave(_) | LogEvent::Sick(_) | LogEvent::Holiday(_) | LogEvent::Toil(_))) && !has(|event| matches!(event, LogEvent::WorkingDay(_) | LogEvent::Work(_))) {
+        for (_, spans) in logs.iter().filter(|(event, _)| matches!(event, LogEvent::Lunch(_))) {
+            lints.push(Lint::LunchOnLeaveDay { day: day.clone(), span: spans.span });
+        }
+    }
+
+    lints
+}
+
+/// Weeks with invalid dates are skipped, as evaluation already reports them
+pub fn lint_timesheets(timesheets: &Timesheets, ctx: &EvalContext) -> Vec<Lint> {
+    let week_start = ctx.config.week_start;
+
+    let mut lints = vec![];
+
+    for Spanned { node: week, spans: week_spans } in &timesheets.weeks {
+        let Ok(starting_date) = week.starting_date(week_start, week_spans) else {
+            continue;
+        };
+
+        let Week(.., Days(days)) = week;
+        let mut latest: Option<DayName> = None;
+
+        for (day, day_spans) in days.iter().zip(&week_spans.days) {
+            let Day(day_name, _) = day;
+            let offset = |DayName(weekday): DayName| (7 + weekday.num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+
+            match latest {
+                Some(latest) if offset(*day_name) < offset(latest) =>
+                    lints.push(Lint::DayOutOfOrder(*day_name, latest, day_spans.day_name)),
+
+                _ => latest = Some(*day_name),
+            }
+
+            lints.append(&mut lint_day(day, day.date(starting_date), day_spans, ctx));
+        }
+    }
+
+    lints
+}
+
+#[cfg(test)]
+fn lints_of(days: &str) -> Vec<String> {
+    let code = format!("WEEK 2026-10-05\n{days}");
+    let ctx = EvalContext::at_utc("2026-10-16 12:00");
+
+    lint_timesheets(&parse_timesheets(&code).unwrap(), &ctx).iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[test]
+fn check_days_out_of_order() {
+    assert_eq!(lints_of("  Tuesday\n    WORKING DAY 09:00 - 17:00\n\n  Monday\n    WORKING DAY 09:00 - 17:00\n"), ["Monday is listed after Tuesday, which comes later in the week"]);
+    assert!(lints_of("  Monday\n    WORKING DAY 09:00 - 17:00\n\n  Tuesday\n    WORKING DAY 09:00 - 17:00\n").is_empty());
+}
+
+#[test]
+fn check_backwards_ranges() {
+    assert_eq!(lints_of("  Friday\n    WORK 22:00 - 06:00 | Release\n"), ["Friday 9th October 2026: 'WORK 22:00 - 06:00' ends before it starts, so runs past midnight"]);
+    assert!(lints_of("  Friday\n    WORK 22:00 - 06:00+1 | Release\n").is_empty());
+}
+
+#[test]
+fn check_overlapping_working_days() {
+    assert_eq!(lints_of("  Monday\n    WORKING DAY 09:00 - 13:00\n    WORKING DAY 12:00 - 17:00\n"), ["Monday 5th October 2026: 'WORKING DAY 12:00 - 17:00' overlaps 'WORKING DAY 09:00 - 13:00' on line 3"]);
+    assert!(lints_of("  Monday\n    WORKING DAY 09:00 - 12:00\n    WORKING DAY 13:00 - 17:00\n").is_empty());
+}
+
+#[test]
+fn check_overlapping_breaks() {
+    assert_eq!(lints_of("  Monday\n    WORKING DAY 09:00 - 17:00\n    BREAK 12:00 - 12:30 | Errand\n    LUNCH 12:15 - 13:00\n"), ["Monday 5th October 2026: 'LUNCH 12:15 - 13:00' overlaps 'BREAK 12:00 - 12:30' on line 4"]);
+    assert!(lints_of("  Monday\n    WORKING DAY 09:00 - 17:00\n    BREAK 11:00 - 11:30 | Errand\n    LUNCH 12:15 - 13:00\n").is_empty());
+}
+
+#[test]
+fn check_breaks_outside_working_day() {
+    assert_eq!(lints_of("  Monday\n    WORKING DAY 09:00 - 17:00\n    BREAK 17:30 - 18:00 | Errand\n"), ["Monday 5th October 2026: 'BREAK 17:30 - 18:00' falls outside of the time worked"]);
+    assert!(lints_of("  Monday\n    WORKING DAY 09:00 - 17:00\n    BREAK 16:30 - 17:00 | Errand\n").is_empty());
+
+    // Overnight, a break is within the time worked on whichever side of midnight it falls
+    assert_eq!(lints_of("  Friday\n    WORKING DAY 22:00 - 06:00+1\n    BREAK 21:30 - 22:30 | Early\n"), ["Friday 9th October 2026: 'BREAK 21:30 - 22:30' falls outside of the time worked"]);
+    assert!(lints_of("  Friday\n    WORKING DAY 22:00 - 06:00+1\n    BREAK 23:45 - 00:15+1 | Snack\n").is_empty());
+
+    // With no ranges of work, there's nothing to say when the break should have been
+    assert!(lints_of("  Monday\n    WORK 8h | Offsite\n    BREAK 17:30 - 18:00 | Errand\n").is_empty());
+}
+
+#[test]
+fn check_lunch_on_leave_day() {
+    assert_eq!(lints_of("  Monday\n    LEAVE 1d\n    LUNCH 30m\n"), ["Monday 5th October 2026: LUNCH is logged on a day off, with no work logged"]);
+    assert!(lints_of("  Monday\n    LEAVE HALF AM\n    WORKING DAY 13:00 - 17:00\n    LUNCH 30m\n").is_empty());
+}
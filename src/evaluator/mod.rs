@@ -1,5 +1,18 @@
+mod context;
+mod deadline;
 mod eval;
+mod json;
+mod leave;
+mod lint;
 mod render;
+mod table;
+mod window;
 
-pub use eval::evaluate_timesheets;
-
+pub use context::EvalContext;
+#[cfg(test)]
+pub use context::evaluate_str;
+pub use eval::{evaluate_timesheets, TotalDelta};
+pub use leave::LeaveLedger;
+pub use lint::lint_timesheets;
+pub use render::{DayReport, Report};
+pub use window::Window;
@@ -1,15 +1,34 @@
-use std::{fmt::Display, iter::once, ops::Not};
+use std::{fmt::Display, iter::once};
 
-use chrono::{DateTime, Local, TimeDelta};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeDelta};
 use itertools::Itertools;
-use once_cell::sync::Lazy;
 
-use crate::{evaluator::eval::{DayDelta, TotalDelta, WeekDelta}, utils::term_render::{Block, Cell, Column, Segment}};
+use crate::{evaluator::{deadline::Deadlines, eval::{Absence, BalanceForwardDelta, DayDelta, LogDelta, TotalDelta, WeekDelta}, leave::{days_to_string, CarriedOver, LeaveLedger, LeaveYear}, EvalContext, Window}, parser::{DayName, LongDate}, utils::term_render::{Block, Cell, Column, Segment, Tone}};
 
-//
-// TODO this should be configuration
-static LUNCH_DURATION: Lazy<TimeDelta>
-    = Lazy::new(|| TimeDelta::minutes(30));
+/// A single day, broken down into its logs and their commentary
+pub struct DayReport<'a>(pub &'a DayDelta);
+
+/// The evaluated timesheets, alongside the context they were evaluated in
+pub struct Report<'a> {
+    pub total_delta: &'a TotalDelta,
+    pub ctx: &'a EvalContext,
+    pub window: Window,
+}
+
+impl Report<'_> {
+    pub fn shown_weeks(&self) -> &[WeekDelta] {
+        &self.total_delta.week_deltas[self.window.select(&self.total_delta.week_deltas)]
+    }
+
+    /// The balance from the weeks before those shown, if there are any
+    pub fn brought_forward(&self) -> Option<TimeDelta> {
+        let shown = self.window.select(&self.total_delta.week_deltas);
+
+        (0 < shown.start).then(|| self.total_delta.week_deltas[..shown.start].iter()
+            .map(WeekDelta::total_delta)
+            .sum())
+    }
+}
 
 
 // This is a bit of a shame.
@@ -31,21 +50,123 @@ fn time_delta_to_string(delta: &TimeDelta) -> String {
     format!("{sign}{time_str:<4}")
 }
 
+fn tone_of(delta: &TimeDelta) -> Tone {
+    match delta.num_minutes().signum() {
+        1 => Tone::Credit,
+        -1 => Tone::Deficit,
+        _ => Tone::Neutral,
+    }
+}
+
+/// As for `tone_of`, with the comment kept bold
+fn heading_tone_of(delta: &TimeDelta) -> Tone {
+    match tone_of(delta) {
+        Tone::Credit => Tone::CreditHeading,
+        Tone::Deficit => Tone::DeficitHeading,
+        _ => Tone::Heading,
+    }
+}
+
 impl From<&DayDelta> for Cell {
-    fn from(DayDelta { weekday, delta, .. }: &DayDelta) -> Self {
+    fn from(DayDelta { date, expected, delta, logged, has_future_time, holiday, .. }: &DayDelta) -> Self {
+        let weekday = DayName(date.weekday());
+
+        // A holiday is named in place of being called a non-working day, and needs nothing logged
+        let notes = [
+            (!logged && holiday.is_none()).then_some("nothing logged yet"),
+            holiday.as_deref().or(expected.is_zero().then_some("non-working day")),
+            has_future_time.then_some("time still to come not counted"),
+        ];
+
+        let notes = notes.into_iter()
+            .flatten()
+            .join(", ");
+
         Cell {
             figure: time_delta_to_string(delta),
-            comment: weekday.to_owned(),
+            comment: match notes.is_empty() {
+                true => weekday.to_string(),
+                false => format!("{weekday} ({notes})"),
+            },
+            tone: tone_of(delta),
         }
     }
 }
 
+impl From<&LogDelta> for Cell {
+    fn from(LogDelta { event, summary, delta }: &LogDelta) -> Self {
+        Cell {
+            figure: time_delta_to_string(delta),
+            comment: match summary {
+                Some(summary) => format!("{event} | {summary}"),
+                None => event.to_owned(),
+            },
+            tone: Tone::Neutral,
+        }
+    }
+}
+
+impl From<&BalanceForwardDelta> for Block {
+    fn from(BalanceForwardDelta { delta, summary }: &BalanceForwardDelta) -> Self {
+        Block(vec![
+            Segment(vec![
+                Cell {
+                    figure: time_delta_to_string(delta),
+                    comment: match summary {
+                        Some(summary) => format!("BALANCE FORWARD | {summary}"),
+                        None => "BALANCE FORWARD".into(),
+                    },
+                    tone: tone_of(delta),
+                }
+            ]),
+        ])
+    }
+}
+
+impl From<&DayReport<'_>> for Block {
+    fn from(DayReport(day_delta): &DayReport<'_>) -> Self {
+        let DayDelta { date, expected, log_deltas, .. } = day_delta;
+
+        let heading = Segment(vec![
+            Cell {
+                comment: format!("{} {}", DayName(date.weekday()), LongDate::from(*date)),
+                ..Cell::from(*day_delta)
+            }
+        ]);
+
+        let logs = log_deltas.iter()
+            .map_into()
+            .collect();
+
+        let expected = Segment(vec![
+            Cell {
+                figure: time_delta_to_string(&-*expected),
+                comment: "EXPECTED".into(),
+                tone: Tone::Neutral,
+            }
+        ]);
+
+        Block(vec![
+            heading,
+            Segment(logs),
+            expected,
+        ])
+    }
+}
+
+impl Display for DayReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", Block::from(self)))
+    }
+}
+
 impl From<&WeekDelta> for Block {
-    fn from(WeekDelta { starting_date, week_delta, day_deltas }: &WeekDelta) -> Self { 
+    fn from(WeekDelta { starting_date, week_delta, day_deltas, .. }: &WeekDelta) -> Self {
         let heading = Segment(vec![
             Cell {
                 figure: time_delta_to_string(week_delta),
-                comment: format!("Week starting {starting_date}"),
+                comment: format!("Week starting {}", LongDate::from(*starting_date)),
+                tone: heading_tone_of(week_delta),
             }
         ]);
         let days = day_deltas.iter()
@@ -59,34 +180,47 @@ impl From<&WeekDelta> for Block {
     }
 }
 
-fn get_deadline_block(total_delta: &TotalDelta) -> Block {
-    // TODO record earliest start time for day
-    // Use that as the reference point to see if you've worked 7.5 hours yet.
-    // Figure out the difference and add that to the time.
-    fn get_lunch_if_not_taken(week_deltas: &[WeekDelta]) -> Option<TimeDelta> {
-        week_deltas.last()
-            ?.day_deltas.last()
-            ?.had_lunch.not()
-            .then_some(*LUNCH_DURATION)
-    }
+/// Each kind of time off taken, leaving out those there's been none of.
+/// TOIL is shown as a deficit, being paid for out of the balance
+fn get_absence_segment(Absence { leave, sick, holiday, toil }: Absence) -> Option<Segment> {
+    let cells: Vec<_> = [(leave, "LEAVE"), (sick, "SICK"), (holiday, "HOLIDAY"), (-toil, "TOIL")]
+        .into_iter()
+        .filter(|(taken, _)| !taken.is_zero())
+        .map(|(taken, kind)| Cell {
+            figure: time_delta_to_string(&taken),
+            comment: format!("{kind} TAKEN"),
+            tone: Tone::Neutral,
+        })
+        .collect();
 
-    fn get_deadline_segment(delta: &TimeDelta, lunch_if_not_taken: Option<&TimeDelta>, message: &str) -> Segment {
-        fn to_string(d: DateTime<Local>) -> String {
+    (!cells.is_empty()).then_some(Segment(cells))
+}
+
+fn get_deadline_block(deadlines: &Deadlines) -> Block {
+    fn get_deadline_segment(deadlines: &Deadlines, deadline: DateTime<FixedOffset>, message: &str) -> Segment {
+        fn to_string(d: DateTime<FixedOffset>) -> String {
             d.format("%H:%M").to_string()
         }
-        let deadline = Local::now() - *delta;
-        let deadline_with_lunch = lunch_if_not_taken.map(|l| to_string(deadline + *l));
 
-        let deadline_cell = Cell {
-            figure: to_string(deadline),
-            comment: message.into(),
+        // A deadline already gone by is when work could have stopped
+        let cell = |deadline: DateTime<FixedOffset>, message: String| match deadlines.has_passed(deadline) {
+            true => Cell {
+                figure: to_string(deadline),
+                comment: format!("{message}, ALREADY PASSED"),
+                tone: Tone::Credit,
+            },
+
+            false => Cell {
+                figure: to_string(deadline),
+                comment: message,
+                tone: Tone::Neutral,
+            },
         };
 
-        let lunch_cell = deadline_with_lunch.map(|lunch| 
-            Cell {
-                figure: lunch,
-                comment: format!("{message} + LUNCH"),
-            });
+        let deadline_cell = cell(deadline, message.into());
+
+        let lunch_cell = deadlines.with_lunch(deadline)
+            .map(|lunch| cell(lunch, format!("{message} + LUNCH")));
 
         let cells = once(deadline_cell)
             .chain(lunch_cell)
@@ -95,22 +229,28 @@ fn get_deadline_block(total_delta: &TotalDelta) -> Block {
         Segment(cells)
     }
 
-    let today_delta = total_delta.week_deltas
-        .last()
-        .and_then(|w| w.day_deltas.last())
-        .map(|day| day.delta)
-        .unwrap_or_else(TimeDelta::zero);
+    // The finish times still hold for an empty day, provided work starts right away
+    let not_started = deadlines.not_started
+        .then(|| Segment(vec![
+            Cell {
+                figure: "--:--".into(),
+                comment: "NOTHING LOGGED TODAY, ASSUMING A START NOW".into(),
+                tone: Tone::Warning,
+            }
+        ]));
 
-    let lunch_if_not_taken = get_lunch_if_not_taken(&total_delta.week_deltas);
+    let segments = not_started.into_iter()
+        .chain([
+            get_deadline_segment(deadlines, deadlines.earliest_finish, "EARLIEST FINISH TIME"),
+            get_deadline_segment(deadlines, deadlines.retain_credit, "RETAIN CREDIT"),
+        ])
+        .collect();
 
-    Block(vec![
-        get_deadline_segment(&total_delta.total_delta, lunch_if_not_taken.as_ref(), "EARLIEST FINISH TIME"),
-        get_deadline_segment(&today_delta, lunch_if_not_taken.as_ref(), "RETAIN CREDIT"),
-    ])
+    Block(segments)
 }
 
-impl From<&TotalDelta> for Column {
-    fn from(value: &TotalDelta) -> Self {
+impl From<&Report<'_>> for Column {
+    fn from(report: &Report<'_>) -> Self {
         fn get_credit_str(delta: &TimeDelta) -> &'static str {
             match delta.num_seconds().is_positive() {
                 true => "CREDIT",
@@ -118,26 +258,53 @@ impl From<&TotalDelta> for Column {
             }
         }
 
-        let summary = Block(vec![
+        let Report { total_delta: value, ctx, .. } = report;
+
+        let totals = vec![
             Segment(vec![
                 Cell {
                     figure: time_delta_to_string(&value.total_delta_excluding_today),
                     comment: format!("TOTAL {} BEFORE TODAY", get_credit_str(&value.total_delta_excluding_today)),
+                    tone: tone_of(&value.total_delta_excluding_today),
                 }
             ]),
             Segment(vec![
                 Cell {
                     figure: time_delta_to_string(&value.total_delta),
                     comment: format!("TOTAL {} NOW", get_credit_str(&value.total_delta)),
+                    tone: tone_of(&value.total_delta),
                 }
             ]),
-        ]);
+        ];
+
+        let summary = Block(totals.into_iter()
+            .chain(get_absence_segment(value.absence()))
+            .collect());
 
-        let deadlines = get_deadline_block(value);
+        let deadlines = get_deadline_block(&Deadlines::new(value, ctx));
 
-        let blocks = value.week_deltas.iter()
-            .rev().take(4).rev() // Past four weeks
+        // Standing in for the weeks left out, so that the weeks shown still add up to the totals
+        let brought_forward = report.brought_forward()
+            .map(|delta| Block(vec![
+                Segment(vec![
+                    Cell {
+                        figure: time_delta_to_string(&delta),
+                        comment: "Brought forward from earlier weeks".into(),
+                        tone: tone_of(&delta),
+                    }
+                ]),
+            ]));
+
+        // Each balance forward gets a line of its own, ahead of the week it was given in
+        let weeks = report.shown_weeks().iter()
+            .flat_map(|week| week.balance_forward.iter()
+                .map_into()
+                .chain(once(Block::from(week))));
+
+        let blocks = value.balance_forward.iter()
             .map_into()
+            .chain(brought_forward)
+            .chain(weeks)
             .chain(once(summary))
             .chain(once(deadlines))
             .collect();
@@ -146,13 +313,150 @@ impl From<&TotalDelta> for Column {
     }
 }
 
-impl Display for TotalDelta {
+impl Display for Report<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if 4 < self.week_deltas.len() {
-            f.write_str("\n      . . .   Previous weeks truncated\n\n")?;
+        f.write_fmt(format_args!("{}", Column::from(self)))
+    }
+}
+
+/// Signed, except for nothing at all
+fn signed_days(days: f64) -> String {
+    match days {
+        days if days_to_string(days.abs()) == "0d" => "0d".into(),
+        days if days < 0.0 => format!("-{}", days_to_string(-days)),
+        days => format!("+{}", days_to_string(days)),
+    }
+}
+
+fn get_leave_year_block(year: &LeaveYear, today: NaiveDate) -> Block {
+    let LeaveYear { start, end, allowance, carried_over, taken, booked, summary } = year;
+
+    let heading = format!("Leave year from {} to {}", LongDate::from(*start), LongDate::from(*end));
+
+    let heading = Segment(vec![
+        Cell {
+            figure: days_to_string(*allowance),
+            comment: match summary {
+                Some(summary) => format!("{heading} | {summary}"),
+                None => heading,
+            },
+            tone: Tone::Heading,
         }
+    ]);
 
-        f.write_fmt(format_args!("{}", Column::from(self)))
+    let carried_over = carried_over.iter()
+        .flat_map(|CarriedOver { days, expires, unused }| {
+            let carried = Cell {
+                figure: signed_days(*days),
+                comment: match expires {
+                    Some(expires) => format!("CARRIED OVER, UNTIL {}", LongDate::from(*expires)),
+                    None => "CARRIED OVER".into(),
+                },
+                tone: Tone::Neutral,
+            };
+
+            let lost = (days_to_string(*unused) != "0d").then(|| Cell {
+                figure: signed_days(-unused),
+                comment: match year.carry_over_has_expired(today) {
+                    true => "CARRIED OVER, EXPIRED UNUSED".into(),
+                    false => "CARRIED OVER, DUE TO EXPIRE UNUSED".into(),
+                },
+                tone: Tone::Warning,
+            });
+
+            once(carried).chain(lost)
+        });
+
+    let used = [(taken, "TAKEN"), (booked, "BOOKED")]
+        .map(|(days, comment)| Cell {
+            figure: signed_days(-days),
+            comment: comment.into(),
+            tone: Tone::Neutral,
+        });
+
+    let remaining = year.remaining();
+
+    let remaining = Segment(vec![
+        Cell {
+            figure: days_to_string(remaining),
+            comment: "REMAINING".into(),
+            tone: match days_to_string(remaining.abs()).as_str() {
+                "0d" => Tone::Neutral,
+                _ if remaining < 0.0 => Tone::Deficit,
+                _ => Tone::Credit,
+            },
+        }
+    ]);
+
+    Block(vec![
+        heading,
+        Segment(carried_over.chain(used).collect()),
+        remaining,
+    ])
+}
+
+impl From<&LeaveLedger> for Column {
+    fn from(LeaveLedger { years, warnings, today }: &LeaveLedger) -> Self {
+        let warnings = (!warnings.is_empty())
+            .then(|| Block(vec![
+                Segment(warnings.iter()
+                    .map(|warning| Cell {
+                        figure: "!".into(),
+                        comment: warning.to_string(),
+                        tone: Tone::Warning,
+                    })
+                    .collect()),
+            ]));
+
+        let blocks = years.iter()
+            .map(|year| get_leave_year_block(year, *today))
+            .chain(warnings)
+            .collect();
+
+        Column(blocks)
     }
 }
 
+#[test]
+fn check_readme_example() {
+    use crate::evaluator::context::evaluate_str;
+
+    let mut ctx = EvalContext::at_utc("2024-09-24 12:01");
+    ctx.config.schedule.set_working_week(TimeDelta::hours(7) + TimeDelta::minutes(30));
+    ctx.config.lunch = TimeDelta::hours(1);
+
+    let total_delta = evaluate_str(include_str!("../../examples/timesheet.tsh"), &ctx);
+
+    let expected = "    ┌───────┐
+    │ +3:04 │ Week starting 16th September 2024
+    ├───────┤
+    │ +1:14 │ Monday
+    │ -6m   │ Tuesday
+    │ +8m   │ Wednesday
+    │ +1:48 │ Thursday
+    │ +0m   │ Friday
+    └───────┘
+    ┌───────┐
+    │ -4:42 │ Week starting 23rd September 2024
+    ├───────┤
+    │ -25m  │ Monday
+    │ -4:17 │ Tuesday
+    └───────┘
+    ┌───────┐
+    │ +2:39 │ TOTAL CREDIT BEFORE TODAY
+    ├───────┤
+    │ -1:38 │ TOTAL DEFICIT NOW
+    ├───────┤
+    │ +7:30 │ LEAVE TAKEN
+    └───────┘
+    ┌───────┐
+    │ 13:39 │ EARLIEST FINISH TIME
+    │ 14:39 │ EARLIEST FINISH TIME + LUNCH
+    ├───────┤
+    │ 16:18 │ RETAIN CREDIT
+    │ 17:18 │ RETAIN CREDIT + LUNCH
+    └───────┘
+";
+
+    assert_eq!(Report { total_delta: &total_delta, ctx: &ctx, window: Window::default() }.to_string(), expected);
+}
@@ -0,0 +1,134 @@
+use chrono::{Datelike, NaiveDate, TimeDelta};
+
+use crate::{evaluator::{eval::{BalanceForwardDelta, DayDelta, LogDelta}, json::date, leave::{days, CarriedOver, LeaveLedger, LeaveYear}, DayReport, Report}, parser::DayName, utils::table_render::Table};
+
+/// e.g. `7:30` or `-0:25`, which spreadsheets read as a duration
+fn hours_minutes(delta: &TimeDelta) -> String {
+    let minutes = delta.num_minutes();
+    let sign = match minutes.is_negative() {
+        true => "-",
+        false => "",
+    };
+
+    format!("{sign}{}:{:0>2}", minutes.abs() / 60, minutes.abs() % 60)
+}
+
+/// Every column but the running balance, along with what the row adds to it
+fn day_row(DayDelta { date: day_date, expected, actual, delta, .. }: &DayDelta) -> (Vec<String>, TimeDelta) {
+    let row = vec![
+        date(day_date),
+        DayName(day_date.weekday()).to_string(),
+        hours_minutes(actual),
+        hours_minutes(expected),
+        hours_minutes(delta),
+    ];
+
+    (row, *delta)
+}
+
+/// Dated by the week it was given in, or left undated at the top of the file
+fn balance_forward_row(week_date: Option<&NaiveDate>, BalanceForwardDelta { delta, .. }: &BalanceForwardDelta) -> (Vec<String>, TimeDelta) {
+    let row = vec![
+        week_date.map(date).unwrap_or_default(),
+        "Balance forward".into(),
+        String::new(),
+        String::new(),
+        hours_minutes(delta),
+    ];
+
+    (row, *delta)
+}
+
+impl Report<'_> {
+    /// One row per day of the weeks shown, and per balance forward,
+    /// with the balance running on from the weeks before them
+    pub fn to_table(&self) -> Table {
+        let headers = ["date", "weekday", "worked", "expected", "delta", "balance"]
+            .map(String::from)
+            .to_vec();
+
+        let weeks = self.shown_weeks().iter()
+            .flat_map(|week| week.balance_forward.iter()
+                .map(|balance_forward| balance_forward_row(Some(&week.starting_date), balance_forward))
+                .chain(week.day_deltas.iter().map(day_row)));
+
+        let rows = self.total_delta.balance_forward.iter()
+            .map(|balance_forward| balance_forward_row(None, balance_forward))
+            .chain(weeks)
+            .scan(self.brought_forward().unwrap_or_default(), |balance, (mut row, delta)| {
+                *balance += delta;
+                row.push(hours_minutes(balance));
+
+                Some(row)
+            })
+            .collect();
+
+        Table { headers, rows }
+    }
+}
+
+impl DayReport<'_> {
+    pub fn to_table(&self) -> Table {
+        let DayReport(DayDelta { log_deltas, .. }) = self;
+
+        let headers = ["log", "commentary", "delta"]
+            .map(String::from)
+            .to_vec();
+
+        let rows = log_deltas.iter()
+            .map(|LogDelta { event, summary, delta }| vec![
+                event.clone(),
+                summary.clone().unwrap_or_default(),
+                hours_minutes(delta),
+            ])
+            .collect();
+
+        Table { headers, rows }
+    }
+}
+
+impl LeaveLedger {
+    /// One row per leave year, leaving the warnings to be given separately
+    pub fn to_table(&self) -> Table {
+        let headers = ["start", "end", "allowance", "carried_over", "carry_over_unused", "taken", "booked", "remaining"]
+            .map(String::from)
+            .to_vec();
+
+        let rows = self.years.iter()
+            .map(|year| {
+                let LeaveYear { start, end, allowance, carried_over, taken, booked, .. } = year;
+                let (carried, unused) = carried_over.as_ref()
+                    .map_or((0.0, 0.0), |CarriedOver { days, unused, .. }| (*days, *unused));
+
+                vec![
+                    date(start),
+                    date(end),
+                    days(*allowance),
+                    days(carried),
+                    days(unused),
+                    days(*taken),
+                    days(*booked),
+                    days(year.remaining()),
+                ]
+            })
+            .collect();
+
+        Table { headers, rows }
+    }
+}
+
+#[test]
+fn check_days_still_to_come_are_not_worked() {
+    use crate::{evaluator::{context::evaluate_str, EvalContext, Window}, utils::table_render::Csv};
+
+    let code = "WEEK 2024-09-23\n  Monday\n    WORKING DAY 09:00 - 17:00\n\n  Wednesday\n    WORKING DAY 09:00 - 17:00\n";
+
+    let ctx = EvalContext::at_utc("2024-09-24 12:00");
+    let total_delta = evaluate_str(code, &ctx);
+    let report = Report { total_delta: &total_delta, ctx: &ctx, window: Window::default() };
+
+    let csv = Csv(&report.to_table()).to_string();
+
+    assert!(csv.contains("\r\n2024-09-23,Monday,8:00,8:00,0:00,0:00\r\n"), "{csv}");
+    assert!(csv.contains("\r\n2024-09-25,Wednesday,0:00,8:00,0:00,-8:00\r\n"), "{csv}");
+}
@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+use chrono::{Datelike, Months, NaiveDate, TimeDelta};
+
+use crate::evaluator::eval::WeekDelta;
+
+/// Which weeks are shown. Totals always cover every week, whichever of them are shown
+#[derive(Debug, Clone, Copy)]
+pub enum Window {
+    /// The most recent weeks
+    Last(usize),
+    All,
+    /// Every week with a day falling between the two dates, inclusive
+    Between { from: Option<NaiveDate>, to: Option<NaiveDate> },
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window::Last(4)
+    }
+}
+
+impl Window {
+    /// The calendar month containing the given date
+    pub fn month(date: NaiveDate) -> Self {
+        let first = date.with_day0(0);
+        let last = first
+            .and_then(|first| first.checked_add_months(Months::new(1)))
+            .and_then(|next| next.pred_opt());
+
+        Window::Between { from: first, to: last }
+    }
+
+    /// The positions of the weeks shown, which are always consecutive as weeks are in date order
+    pub fn select(&self, week_deltas: &[WeekDelta]) -> Range<usize> {
+        match self {
+            Window::Last(weeks) => week_deltas.len().saturating_sub(*weeks)..week_deltas.len(),
+
+            Window::All => 0..week_deltas.len(),
+
+            Window::Between { from, to } => {
+                let start = week_deltas.iter()
+                    .position(|week| from.is_none_or(|from| from <= week.starting_date + TimeDelta::days(6)))
+                    .unwrap_or(week_deltas.len());
+
+                let end = week_deltas.iter()
+                    .rposition(|week| to.is_none_or(|to| week.starting_date <= to))
+                    .map_or(0, |last| last + 1);
+
+                start..end.max(start)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+fn weeks_starting(dates: &[&str]) -> Vec<WeekDelta> {
+    dates.iter()
+        .map(|date| WeekDelta {
+            starting_date: date.parse().unwrap(),
+            week_delta: TimeDelta::zero(),
+            balance_forward: None,
+            day_deltas: vec![],
+        })
+        .collect()
+}
+
+#[test]
+fn check_last_weeks_stop_at_the_first() {
+    let weeks = weeks_starting(&["2024-09-02", "2024-09-09", "2024-09-16"]);
+
+    assert_eq!(Window::Last(2).select(&weeks), 1..3);
+    assert_eq!(Window::Last(10).select(&weeks), 0..3);
+    assert_eq!(Window::Last(0).select(&weeks), 3..3);
+    assert_eq!(Window::Last(4).select(&[]), 0..0);
+}
+
+#[test]
+fn check_between_with_no_weeks_is_empty_where_they_would_be() {
+    let weeks = weeks_starting(&["2024-09-02", "2024-09-09", "2024-09-23"]);
+    let between = |from: &str, to: &str| Window::Between { from: from.parse().ok(), to: to.parse().ok() };
+
+    // Everything before the gap is brought forward, so the window has to start after it
+    assert_eq!(between("2024-09-16", "2024-09-22").select(&weeks), 2..2);
+    assert_eq!(between("2024-10-01", "2024-10-31").select(&weeks), 3..3);
+    assert_eq!(between("2024-08-01", "2024-08-31").select(&weeks), 0..0);
+    assert_eq!(between("2024-09-23", "2024-09-02").select(&weeks), 2..2);
+    assert_eq!(between("2024-09-10", "").select(&weeks), 1..3);
+}
+
+#[test]
+fn check_months_across_the_turn_of_the_year() {
+    let date = |date: &str| date.parse::<NaiveDate>().ok();
+
+    assert!(matches!(Window::month("2024-12-15".parse().unwrap()), Window::Between { from, to } if from == date("2024-12-01") && to == date("2024-12-31")));
+    assert!(matches!(Window::month("2025-01-31".parse().unwrap()), Window::Between { from, to } if from == date("2025-01-01") && to == date("2025-01-31")));
+
+    // The week starting 30th December falls in both months
+    let weeks = weeks_starting(&["2024-12-23", "2024-12-30", "2025-01-06"]);
+
+    assert_eq!(Window::month("2024-12-15".parse().unwrap()).select(&weeks), 0..2);
+    assert_eq!(Window::month("2025-01-31".parse().unwrap()).select(&weeks), 1..3);
+}
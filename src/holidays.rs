@@ -0,0 +1,164 @@
+/*
+ * Public holidays are read from a local file named in the config, so that they needn't be logged by hand.
+ * Either a list with a holiday to a line, e.g. `2024-12-25 | Christmas Day`,
+ * or an iCalendar file (`.ics`) of all-day events, such as those published for bank holidays
+ */
+
+use std::{fs::read_to_string, io, path::{Path, PathBuf}};
+
+use chrono::{NaiveDate, TimeDelta};
+use thiserror::Error;
+
+use crate::parser::Date;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+#[derive(Error, Debug)]
+pub enum HolidaysError {
+    #[error("Unable to read holidays file {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("Invalid holiday on line {line} of {}: {message}", path.display())]
+    Invalid { path: PathBuf, line: usize, message: String },
+}
+
+/// Named when the list doesn't give a name of its own
+const UNNAMED: &str = "Public holiday";
+
+/// A holiday to a line, with its date written as for WEEK and an optional name.
+/// Blank lines and those starting with `#` are skipped
+fn parse_list(text: &str) -> Result<Vec<Holiday>, (usize, String)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, text)| {
+            let (date, name) = text.split_once('|')
+                .unwrap_or((text, UNNAMED));
+
+            let date = date.trim().parse::<Date>()
+                .ok()
+                .and_then(|date| date.to_naive_date())
+                .ok_or_else(|| (line, format!("'{}' is not a date such as 2024-12-25", date.trim())))?;
+
+            Ok(Holiday { date, name: name.trim().to_owned() })
+        })
+        .collect()
+}
+
+/// The date at the start of a DATE or DATE-TIME value, e.g. `20241225` or `20241225T000000Z`
+fn parse_ics_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+fn unescape_ics_text(value: &str) -> String {
+    value.replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// A VEVENT as far as it's been read, starting on the given line
+struct Event {
+    line: usize,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    summary: Option<String>,
+}
+
+/// Every day of each event, taking DTEND as the day after the event ends, as iCalendar does.
+/// Recurring events are turned away, rather than being taken to happen only once
+fn parse_ics(text: &str) -> Result<Vec<Holiday>, (usize, String)> {
+    // Long lines are folded onto those that follow, each starting with a space or a tab
+    let mut lines: Vec<(usize, String)> = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(folded), Some((_, last))) => last.push_str(folded),
+            _ => lines.push((i + 1, line.to_owned())),
+        }
+    }
+
+    let mut holidays = vec![];
+    let mut event: Option<Event> = None;
+
+    for (line, text) in lines {
+        let Some((name, value)) = text.split_once(':') else {
+            continue;
+        };
+
+        // Parameters, such as `;VALUE=DATE`, make no difference to the dates
+        let name = name.split(';').next().unwrap_or(name);
+
+        match (name, &mut event) {
+            ("BEGIN", None) if value == "VEVENT" =>
+                event = Some(Event { line, start: None, end: None, summary: None }),
+
+            ("DTSTART", Some(event)) =>
+                event.start = Some(parse_ics_date(value).ok_or_else(|| (line, format!("'{value}' is not a date")))?),
+
+            ("DTEND", Some(event)) =>
+                event.end = Some(parse_ics_date(value).ok_or_else(|| (line, format!("'{value}' is not a date")))?),
+
+            ("SUMMARY", Some(event)) =>
+                event.summary = Some(unescape_ics_text(value)),
+
+            ("RRULE", Some(_)) =>
+                return Err((line, "recurring events aren't supported, so each holiday must be an event of its own".into())),
+
+            ("END", Some(Event { line: begin, start, end, summary })) if value == "VEVENT" => {
+                let start = start.ok_or_else(|| (*begin, "the event has no DTSTART".to_owned()))?;
+                let name = summary.take().unwrap_or_else(|| UNNAMED.into());
+                let days = end.map_or(1, |end| (end - start).num_days().max(1));
+
+                holidays.extend((0..days).map(|day| Holiday {
+                    date: start + TimeDelta::days(day),
+                    name: name.clone(),
+                }));
+
+                event = None;
+            },
+
+            _ => {},
+        }
+    }
+
+    Ok(holidays)
+}
+
+/// Read as iCalendar if the file ends in `.ics`, or as a list otherwise
+pub fn load_holidays(path: &Path) -> Result<Vec<Holiday>, HolidaysError> {
+    let text = read_to_string(path)
+        .map_err(|source| HolidaysError::Read { path: path.to_owned(), source })?;
+
+    let is_ics = path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ics"));
+
+    let holidays = match is_ics {
+        true => parse_ics(&text),
+        false => parse_list(&text),
+    };
+
+    holidays.map_err(|(line, message)| HolidaysError::Invalid { path: path.to_owned(), line, message })
+}
+
+#[test]
+fn check_ics_events_are_read_as_holidays() {
+    let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20241225\r\nDTEND;VALUE=DATE:20241227\r\nSUMMARY:Christmas Day\\, and Boxing \r\n Day\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nSUMMARY:New Year’s Day\r\nDTSTART;VALUE=DATE:20250101\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+    let holidays: Vec<_> = parse_ics(ics).unwrap()
+        .into_iter()
+        .map(|Holiday { date, name }| (date.to_string(), name))
+        .collect();
+
+    assert_eq!(holidays, [
+        ("2024-12-25".into(), "Christmas Day, and Boxing Day".into()),
+        ("2024-12-26".into(), "Christmas Day, and Boxing Day".into()),
+        ("2025-01-01".into(), "New Year’s Day".into()),
+    ]);
+}
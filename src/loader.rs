@@ -0,0 +1,367 @@
+/*
+ * A timesheet can be split across several files with `INCLUDE "2024/09.tsh"`, resolved relative to the file it's in.
+ * Every file is read and parsed up front, then their weeks are merged into a single timesheet in date order,
+ * with each span marked with the file it came from so that diagnostics point back to the right place
+ */
+
+use std::{fs::read_to_string, io, path::{Path, PathBuf}};
+
+use thiserror::Error;
+
+use crate::{parser::*, utils::diagnostic::{Diagnostic, Severity}};
+
+/// A file that has been read, along with its contents
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub path: PathBuf,
+    pub code: String,
+}
+
+#[derive(Error, Debug)]
+pub enum IncludeError {
+    #[error("INCLUDE \"{0}\" can't be read: {1}")]
+    Unreadable(String, io::Error, Span),
+
+    #[error("INCLUDE \"{0}\" matches no files")]
+    NoMatches(String, Span),
+
+    #[error("INCLUDE \"{0}\" is not a valid pattern: {1}")]
+    InvalidPattern(String, glob::PatternError, Span),
+
+    #[error("INCLUDE \"{path}\" leads back to {}, which is already being read", back_to.display())]
+    Cycle { path: String, back_to: PathBuf, span: Span },
+
+    #[error("WEEK {week} is also in {}, on line {line}", other.display())]
+    DuplicateWeek { week: String, other: PathBuf, line: usize, span: Span },
+
+    #[error("BALANCE FORWARD is also given at the top of {}, on line {line}", other.display())]
+    DuplicateBalanceForward { other: PathBuf, line: usize, span: Span },
+}
+
+impl IncludeError {
+    pub fn span(&self) -> Span {
+        match self {
+            IncludeError::Unreadable(_, _, span)
+            | IncludeError::NoMatches(_, span)
+            | IncludeError::InvalidPattern(_, _, span)
+            | IncludeError::Cycle { span, .. }
+            | IncludeError::DuplicateWeek { span, .. }
+            | IncludeError::DuplicateBalanceForward { span, .. } => *span,
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            IncludeError::Unreadable(..)
+            | IncludeError::NoMatches(..) =>
+                Some("paths are relative to the file the INCLUDE is in".into()),
+
+            IncludeError::InvalidPattern(..) =>
+                None,
+
+            IncludeError::Cycle { .. } =>
+                Some("a file can't include itself, whether directly or through the files it includes".into()),
+
+            IncludeError::DuplicateWeek { .. } =>
+                Some("move the days of this week into the other file".into()),
+
+            IncludeError::DuplicateBalanceForward { .. } =>
+                Some("keep a single opening balance, or move one beneath the WEEK it applies from".into()),
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: self.to_string(),
+            span: self.span(),
+            hint: self.hint(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file given on the command line couldn't be read
+    Read(io::Error),
+    Parsing(ParsingError),
+    Includes(Vec<Source>, Vec<IncludeError>),
+}
+
+/// Every file read, and the timesheet they make up between them
+pub struct Loaded {
+    pub sources: Vec<Source>,
+    pub timesheets: Timesheets,
+}
+
+#[derive(Default)]
+struct Loader {
+    sources: Vec<Source>,
+    parsed: Vec<Timesheets>,
+    /// Where each loaded file really is, so that it's only read once however it's reached
+    canonical: Vec<PathBuf>,
+    errors: Vec<IncludeError>,
+}
+
+fn is_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+impl Loader {
+    /// The files an INCLUDE refers to, with those matching a pattern in alphabetical order
+    fn resolve(&mut self, including: &Path, path: &str, span: Span) -> Vec<PathBuf> {
+        let joined = including.parent()
+            .unwrap_or(Path::new(""))
+            .join(path);
+
+        if !is_pattern(path) {
+            return vec![joined];
+        }
+
+        match glob::glob(&joined.to_string_lossy()) {
+            Ok(paths) => {
+                let mut paths: Vec<_> = paths.flatten().collect();
+                paths.sort();
+
+                if paths.is_empty() {
+                    self.errors.push(IncludeError::NoMatches(path.into(), span));
+                }
+
+                paths
+            },
+
+            Err(err) => {
+                self.errors.push(IncludeError::InvalidPattern(path.into(), err, span));
+                vec![]
+            },
+        }
+    }
+
+    /// Parses the file, then loads everything it includes, depth first.
+    /// `reading` holds the files that led here, any of which being included again would never end
+    fn load(&mut self, path: PathBuf, canonical: PathBuf, code: String, reading: &mut Vec<PathBuf>) -> Result<(), LoadError> {
+        let file = self.sources.len();
+
+        let timesheets = parse_timesheets(&code)
+            .map_err(|err| match err {
+                ParsingError::PestError(err) =>
+                    ParsingError::PestError(Box::new(err.with_path(&path.display().to_string()))),
+
+                err => err,
+            })
+            .map_err(LoadError::Parsing)?;
+
+        let Timesheets { includes, balance_forward, contracts, allowances, weeks } = timesheets;
+
+        self.sources.push(Source { path: path.clone(), code });
+        self.canonical.push(canonical.clone());
+        self.parsed.push(Timesheets {
+            includes: vec![],
+            balance_forward: balance_forward.map(|balance_forward| balance_forward.in_file(file)),
+            contracts: contracts.into_iter().map(|contract| contract.in_file(file)).collect(),
+            allowances: allowances.into_iter().map(|allowance| allowance.in_file(file)).collect(),
+            weeks: weeks.into_iter().map(|week| week.in_file(file)).collect(),
+        });
+
+        reading.push(canonical);
+
+        for Spanned { node: Include(IncludePath(include)), spans: span } in includes {
+            let span = span.in_file(file);
+
+            for included in self.resolve(&path, &include, span) {
+                let found = included.canonicalize()
+                    .and_then(|canonical| Ok((read_to_string(&included)?, canonical)));
+
+                let (code, canonical) = match found {
+                    Ok(found) => found,
+                    Err(err) => {
+                        self.errors.push(IncludeError::Unreadable(include.clone(), err, span));
+                        continue;
+                    },
+                };
+
+                if reading.contains(&canonical) {
+                    let back_to = self.canonical.iter()
+                        .position(|loaded| *loaded == canonical)
+                        .map_or(included, |loaded| self.sources[loaded].path.clone());
+
+                    self.errors.push(IncludeError::Cycle { path: include.clone(), back_to, span });
+                    continue;
+                }
+
+                // Matched by more than one INCLUDE, such as a pattern and a path to the same file
+                if self.canonical.contains(&canonical) {
+                    continue;
+                }
+
+                self.load(included, canonical, code, reading)?;
+            }
+        }
+
+        reading.pop();
+
+        Ok(())
+    }
+
+    /// A single timesheet holding every week, in date order, with a single opening balance at most.
+    /// Contracts and allowances are kept in the order they were read, as evaluation sorts them once their dates are known
+    fn merge(mut self) -> Result<Loaded, LoadError> {
+        let mut balance_forward: Option<Spanned<BalanceForward>> = None;
+        let mut contracts = vec![];
+        let mut allowances = vec![];
+        let mut weeks: Vec<Spanned<Week, WeekSpans>> = vec![];
+
+        for Timesheets { balance_forward: file_balance_forward, contracts: file_contracts, allowances: file_allowances, weeks: file_weeks, .. } in self.parsed {
+            contracts.extend(file_contracts);
+            allowances.extend(file_allowances);
+
+            match (&balance_forward, file_balance_forward) {
+                (Some(Spanned { spans: first, .. }), Some(Spanned { spans: span, .. })) => self.errors.push(IncludeError::DuplicateBalanceForward {
+                    other: self.sources[first.file].path.clone(),
+                    line: first.line,
+                    span,
+                }),
+
+                (None, file_balance_forward) => balance_forward = file_balance_forward,
+
+                _ => {},
+            }
+
+            for week in file_weeks {
+                let Spanned { node: Week(date, ..), spans: week_spans } = &week;
+
+                let duplicate = date.to_naive_date().and_then(|starting_date| weeks.iter()
+                    .find(|Spanned { node: Week(other, ..), spans: other_spans }| other_spans.span.file != week_spans.span.file && other.to_naive_date() == Some(starting_date)));
+
+                if let Some(Spanned { spans: other_spans, .. }) = duplicate {
+                    self.errors.push(IncludeError::DuplicateWeek {
+                        week: date.to_string(),
+                        other: self.sources[other_spans.span.file].path.clone(),
+                        line: other_spans.span.line,
+                        span: week_spans.date,
+                    });
+                }
+
+                weeks.push(week);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            return Err(LoadError::Includes(self.sources, self.errors));
+        }
+
+        // Weeks with invalid dates are left at the front, for evaluation to report
+        weeks.sort_by_key(|Spanned { node: Week(date, ..), .. }| date.to_naive_date());
+
+        Ok(Loaded {
+            sources: self.sources,
+            timesheets: Timesheets { includes: vec![], balance_forward, contracts, allowances, weeks },
+        })
+    }
+}
+
+/// Reads the timesheet at the given path, along with every file it includes
+pub fn load_timesheets(path: &Path) -> Result<Loaded, LoadError> {
+    let code = read_to_string(path)
+        .map_err(LoadError::Read)?;
+
+    let canonical = path.canonicalize()
+        .map_err(LoadError::Read)?;
+
+    let mut loader = Loader::default();
+    loader.load(path.to_owned(), canonical, code, &mut vec![])?;
+    loader.merge()
+}
+
+#[test]
+fn check_included_weeks_are_merged_in_date_order() {
+    let dir = std::env::temp_dir().join(format!("timesheets-include-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("2024")).unwrap();
+
+    std::fs::write(dir.join("main.tsh"), "INCLUDE \"2024/*.tsh\"\n\nWEEK 2024-09-23\n  Monday\n    WORKING DAY 09:00 - 17:00\n").unwrap();
+    std::fs::write(dir.join("2024/09.tsh"), "WEEK 2024-09-16\n  Monday\n    WORK 1h\n").unwrap();
+
+    let Loaded { sources, timesheets } = load_timesheets(&dir.join("main.tsh")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let dates: Vec<_> = timesheets.weeks.iter().map(|Spanned { node: Week(date, ..), .. }| date.to_string()).collect();
+
+    assert_eq!(dates, ["2024-09-16", "2024-09-23"]);
+    assert_eq!(sources[timesheets.weeks[0].spans.days[0].logs[0].span.file].path, dir.join("2024/09.tsh"));
+}
+
+/// Writes the files into a directory of their own, then loads `main.tsh` from it
+#[cfg(test)]
+fn load_files(name: &str, files: &[(&str, &str)]) -> (PathBuf, Result<Loaded, LoadError>) {
+    let dir = std::env::temp_dir().join(format!("timesheets-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (path, code) in files {
+        std::fs::write(dir.join(path), code).unwrap();
+    }
+
+    let loaded = load_timesheets(&dir.join("main.tsh"));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    (dir, loaded)
+}
+
+#[cfg(test)]
+fn include_errors(loaded: Result<Loaded, LoadError>) -> Vec<IncludeError> {
+    match loaded {
+        Err(LoadError::Includes(_, errors)) => errors,
+        Err(err) => panic!("Expected include errors, not {err:?}"),
+        Ok(_) => panic!("Expected include errors"),
+    }
+}
+
+#[test]
+fn check_include_cycles_are_reported() {
+    let (dir, loaded) = load_files("cycle", &[
+        ("main.tsh", "INCLUDE \"a.tsh\"\n"),
+        ("a.tsh", "INCLUDE \"main.tsh\"\n"),
+    ]);
+
+    let errors = include_errors(loaded);
+
+    assert!(matches!(&errors[..], [IncludeError::Cycle { path, back_to, .. }] if path == "main.tsh" && *back_to == dir.join("main.tsh")));
+}
+
+#[test]
+fn check_weeks_in_more_than_one_file_are_reported() {
+    let (dir, loaded) = load_files("duplicate-week", &[
+        ("main.tsh", "INCLUDE \"a.tsh\"\n\nWEEK 2024-09-16\n  Monday\n    WORK 1h\n"),
+        ("a.tsh", "WEEK 16th September 2024\n  Tuesday\n    WORK 1h\n"),
+    ]);
+
+    let errors = include_errors(loaded);
+
+    assert!(matches!(&errors[..], [IncludeError::DuplicateWeek { week, other, line: 3, .. }] if week == "16th September 2024" && *other == dir.join("main.tsh")));
+}
+
+#[test]
+fn check_balance_forward_in_more_than_one_file_is_reported() {
+    let (dir, loaded) = load_files("duplicate-balance", &[
+        ("main.tsh", "INCLUDE \"a.tsh\"\n\nBALANCE FORWARD +1h\n"),
+        ("a.tsh", "BALANCE FORWARD -30m\n"),
+    ]);
+
+    let errors = include_errors(loaded);
+
+    assert!(matches!(&errors[..], [IncludeError::DuplicateBalanceForward { other, line: 3, .. }] if *other == dir.join("main.tsh")));
+}
+
+#[test]
+fn check_a_file_reached_twice_is_loaded_once() {
+    let (_, loaded) = load_files("diamond", &[
+        ("main.tsh", "INCLUDE \"a.tsh\"\nINCLUDE \"b.tsh\"\n"),
+        ("a.tsh", "INCLUDE \"shared.tsh\"\n"),
+        ("b.tsh", "INCLUDE \"shared.tsh\"\n"),
+        ("shared.tsh", "WEEK 2024-09-16\n  Monday\n    WORK 1h\n"),
+    ]);
+
+    let Loaded { sources, timesheets: Timesheets { weeks, .. } } = loaded.unwrap();
+
+    assert_eq!(sources.len(), 4);
+    assert_eq!(weeks.len(), 1);
+}
@@ -1,31 +1,36 @@
-use std::{fs::read_to_string, path::{Path, PathBuf}};
+use std::{fs::read_to_string, path::{Path, PathBuf}, process::ExitCode};
 
-use clap::Parser;
-use cli::{parse_cli, Action, TshArgs};
-use evaluator::evaluate_timesheets;
-use parser::{parse_timesheets, ParsingError};
+use chrono::NaiveDate;
+
+use cli::{parse_cli, Action, OutputFormat, TshArgs};
+use config::{Config, ConfigError};
+use editor::{end_log, format_timesheet, start_log, EditError, Edited, FormatError};
+use evaluator::{evaluate_timesheets, lint_timesheets, DayReport, EvalContext, LeaveLedger, Report, TotalDelta, Window};
+use loader::{load_timesheets, LoadError, Loaded};
+use parser::{LongDate, ParsingError, Time};
+use utils::{diagnostic::FileDiagnostics, table_render::{Csv, Markdown}, term_render::{Block, Column, Style, Styled}};
 
 mod cli;
+mod config;
+mod editor;
 mod parser;
 mod evaluator;
+mod holidays;
+mod loader;
 mod utils;
 
-#[derive(Parser, Debug)]
-#[command(version, about)]
-struct Args {
-    file_path: PathBuf,
-}
-
-// TODO new features:
-/* Open given log-type with given time (or current, if not specified)
- * End open log-type with given time (or current, if not specified)
- * Have a special flag to end the working day with the previous command
- */
-
 #[derive(Debug)]
 enum TimesheetsError {
     FileReadError(std::io::Error),
     ParsingError(ParsingError),
+    Config(ConfigError),
+    Diagnostics(FileDiagnostics),
+    DayNotFound(NaiveDate),
+    NoAllowance(PathBuf),
+    FileWriteError(std::io::Error),
+    Edit(EditError),
+    Format(FormatError),
+    NotFormatted(PathBuf),
 }
 
 impl std::fmt::Display for TimesheetsError {
@@ -33,41 +38,228 @@ impl std::fmt::Display for TimesheetsError {
         match self {
             TimesheetsError::FileReadError(err) => err.fmt(f),
             TimesheetsError::ParsingError(err) => err.fmt(f),
+            TimesheetsError::Config(err) => err.fmt(f),
+            TimesheetsError::Diagnostics(diagnostics) => diagnostics.fmt(f),
+            TimesheetsError::DayNotFound(date) =>
+                f.write_fmt(format_args!("Nothing has been logged for {}", LongDate::from(*date))),
+            TimesheetsError::NoAllowance(path) =>
+                f.write_fmt(format_args!("{} has no ALLOWANCE to track leave against, e.g. 'ALLOWANCE 25d FROM 1st January 2024'", path.display())),
+            TimesheetsError::FileWriteError(err) => err.fmt(f),
+            TimesheetsError::Edit(err) => err.fmt(f),
+            TimesheetsError::Format(err) => err.fmt(f),
+            TimesheetsError::NotFormatted(path) =>
+                f.write_fmt(format_args!("{} is not formatted, run `timesheets {} fmt` to fix", path.display(), path.display())),
         }
     }
 }
 
-fn run_timesheets(path: &Path) -> Result<(), TimesheetsError> {
-    let code = read_to_string(path)
-        .map_err(TimesheetsError::FileReadError)?;
+/// The timesheet, merged with every file it includes
+fn parse_file(path: &Path) -> Result<Loaded, TimesheetsError> {
+    load_timesheets(path)
+        .map_err(|err| match err {
+            LoadError::Read(err) => TimesheetsError::FileReadError(err),
+            LoadError::Parsing(err) => TimesheetsError::ParsingError(err),
+            LoadError::Includes(sources, errors) => {
+                let diagnostics = errors.iter()
+                    .map(|error| error.to_diagnostic())
+                    .collect();
+
+                TimesheetsError::Diagnostics(FileDiagnostics::new(sources, diagnostics))
+            },
+        })
+}
+
+fn evaluate_file(path: &Path, ctx: &EvalContext) -> Result<TotalDelta, TimesheetsError> {
+    let Loaded { sources, timesheets } = parse_file(path)?;
 
-    let timesheets = parse_timesheets(&code)
-        .map_err(TimesheetsError::ParsingError)?;
+    evaluate_timesheets(timesheets, ctx)
+        .map_err(|errors| {
+            let diagnostics = errors.iter()
+                .map(|error| error.to_diagnostic())
+                .collect();
 
-    let total_delta = evaluate_timesheets(timesheets);
+            TimesheetsError::Diagnostics(FileDiagnostics::new(sources, diagnostics))
+        })
+}
+
+/// Fails only on errors, with warnings alone being printed
+fn run_check(path: &Path, ctx: &EvalContext) -> Result<(), TimesheetsError> {
+    let Loaded { sources, timesheets } = parse_file(path)?;
+
+    let lints = lint_timesheets(&timesheets, ctx)
+        .iter()
+        .map(|lint| lint.to_diagnostic())
+        .collect::<Vec<_>>();
 
-    print!("{total_delta}");
+    let errors = evaluate_timesheets(timesheets, ctx)
+        .err()
+        .unwrap_or_default()
+        .iter()
+        .map(|error| error.to_diagnostic())
+        .collect::<Vec<_>>();
+
+    let diagnostics = FileDiagnostics::new(sources, [lints, errors].concat());
+
+    if diagnostics.has_errors() {
+        return Err(TimesheetsError::Diagnostics(diagnostics));
+    }
+
+    match diagnostics.diagnostics.is_empty() {
+        true => println!("No problems found in {}", path.display()),
+        false => eprintln!("{diagnostics}"),
+    }
 
     Ok(())
 }
 
-fn main() {
-    match parse_cli() {
-        TshArgs { file_path, command: None } => 
-            match run_timesheets(&file_path) {
-                Ok(_) => (),
-                Err(err) => eprintln!("{}", err),
-            }
-
-        // Still experimental
-        TshArgs { file_path: _file_path, command: Some(command) } => {
-            match command {
-                Action::Start { log_type, time_range } =>
-                    println!("{log_type:?}: {time_range}"),
-
-                Action::End { log_type, time_range } =>
-                    println!("{log_type:?}: {time_range}"),
-            }
-        }
+fn run_timesheets(path: &Path, format: OutputFormat, style: Style, window: Window, ctx: &EvalContext) -> Result<(), TimesheetsError> {
+    let total_delta = evaluate_file(path, ctx)?;
+    let report = Report { total_delta: &total_delta, ctx, window };
+
+    match format {
+        OutputFormat::Human => print!("{}", Styled(&Column::from(&report), style)),
+        OutputFormat::Json => println!("{}", report.to_json()),
+        OutputFormat::Csv => print!("{}", Csv(&report.to_table())),
+        OutputFormat::Markdown => print!("{}", Markdown(&report.to_table())),
+    }
+
+    Ok(())
+}
+
+fn run_show(path: &Path, date: NaiveDate, format: OutputFormat, style: Style, ctx: &EvalContext) -> Result<(), TimesheetsError> {
+    let total_delta = evaluate_file(path, ctx)?;
+
+    let day_delta = total_delta.day(date)
+        .ok_or(TimesheetsError::DayNotFound(date))?;
+
+    match format {
+        OutputFormat::Human => print!("{}", Styled(&Block::from(&DayReport(day_delta)), style)),
+        OutputFormat::Json => println!("{}", DayReport(day_delta).to_json()),
+        OutputFormat::Csv => print!("{}", Csv(&DayReport(day_delta).to_table())),
+        OutputFormat::Markdown => print!("{}", Markdown(&DayReport(day_delta).to_table())),
+    }
+
+    Ok(())
+}
+
+/// Warnings are printed alongside the boxes, or to stderr for the table formats, which have nowhere to put them
+fn run_leave(path: &Path, format: OutputFormat, style: Style, ctx: &EvalContext) -> Result<(), TimesheetsError> {
+    let total_delta = evaluate_file(path, ctx)?;
+
+    if total_delta.allowances.is_empty() {
+        return Err(TimesheetsError::NoAllowance(path.to_owned()));
+    }
+
+    let ledger = LeaveLedger::new(&total_delta);
+
+    let warn = || ledger.warnings.iter()
+        .for_each(|warning| eprintln!("warning: {warning}"));
+
+    match format {
+        OutputFormat::Human => print!("{}", Styled(&Column::from(&ledger), style)),
+        OutputFormat::Json => println!("{}", ledger.to_json()),
+        OutputFormat::Csv => {
+            print!("{}", Csv(&ledger.to_table()));
+            warn();
+        },
+        OutputFormat::Markdown => {
+            print!("{}", Markdown(&ledger.to_table()));
+            warn();
+        },
+    }
+
+    Ok(())
+}
+
+/// Applies an edit to the timesheet, treating a file that doesn't exist yet as empty
+fn run_edit<F>(path: &Path, edit: F) -> Result<(), TimesheetsError>
+where
+    F: FnOnce(&str) -> Result<Edited, EditError>,
+{
+    let code = match read_to_string(path) {
+        Ok(code) => code,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(TimesheetsError::FileReadError(err)),
+    };
+
+    let Edited { code, line, date } = edit(&code)
+        .map_err(TimesheetsError::Edit)?;
+
+    std::fs::write(path, code)
+        .map_err(TimesheetsError::FileWriteError)?;
+
+    println!("{}: {line}", LongDate::from(date));
+
+    Ok(())
+}
+
+fn run_fmt(path: &Path, check: bool) -> Result<(), TimesheetsError> {
+    let code = read_to_string(path)
+        .map_err(TimesheetsError::FileReadError)?;
+
+    let formatted = format_timesheet(&code)
+        .map_err(TimesheetsError::Format)?;
+
+    match (formatted == code, check) {
+        (true, _) => Ok(()),
+        (false, true) => Err(TimesheetsError::NotFormatted(path.to_owned())),
+        (false, false) => std::fs::write(path, formatted)
+            .map_err(TimesheetsError::FileWriteError),
     }
 }
+
+fn report(result: Result<(), TimesheetsError>) -> ExitCode {
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        },
+    }
+}
+
+fn main() -> ExitCode {
+    let args = parse_cli();
+
+    let config = match Config::load(&args) {
+        Ok(config) => config,
+        Err(err) => return report(Err(TimesheetsError::Config(err))),
+    };
+
+    let ctx = match args.at {
+        Some(at) => EvalContext::at(at, config),
+        None => EvalContext::new(config),
+    };
+
+    let window = args.window()
+        .unwrap_or_else(|err| err.exit());
+    let style = Style::for_terminal(args.ascii, args.color.enabled());
+
+    let result = match args {
+        TshArgs { file_path, format, command: None, .. } =>
+            run_timesheets(&file_path, format, style, window, &ctx),
+
+        TshArgs { file_path, format, command: Some(Action::Show { date }), .. } =>
+            run_show(&file_path, date, format, style, &ctx),
+
+        TshArgs { file_path, command: Some(Action::Start { log_type, time_range, commentary }), .. } => {
+            let time = time_range.unwrap_or(Time(ctx.time()));
+
+            run_edit(&file_path, |code| start_log(code, &log_type, time, commentary.as_deref(), &ctx))
+        }
+
+        TshArgs { file_path, command: Some(Action::End { log_type, time_range }), .. } =>
+            run_edit(&file_path, |code| end_log(code, &log_type, time_range, &ctx)),
+
+        TshArgs { file_path, command: Some(Action::Fmt { check }), .. } =>
+            run_fmt(&file_path, check),
+
+        TshArgs { file_path, command: Some(Action::Check), .. } =>
+            run_check(&file_path, &ctx),
+
+        TshArgs { file_path, format, command: Some(Action::Leave), .. } =>
+            run_leave(&file_path, format, style, &ctx),
+    };
+
+    report(result)
+}
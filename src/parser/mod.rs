@@ -1,16 +1,52 @@
-use pest::iterators::Pairs;
+use pest::iterators::Pair;
 use lang_packer_model::{
     generic_utils::{PackingError, SyntaxTree},
-    pack_trees::{unpack_only_tree, TokenPacker}
+    pack_trees::{HasRule, TokenPacker}
 };
 
 use parser_impl::parse;
 
 mod parser_impl;
 mod packer;
+mod spans;
 
 pub use parser_impl::{TimesheetsParser, Rule};
 pub use packer::*;
+pub use spans::*;
+
+/// A node of the packed AST, along with where it was found in the source.
+/// Both are taken from the same pair, so they can't fall out of step with each other
+#[derive(Debug, Clone)]
+pub struct Spanned<T, S = Span> {
+    pub node: T,
+    pub spans: S,
+}
+
+/// Each part of the packed AST, and where it was found in the source
+#[derive(Debug)]
+pub struct Timesheets {
+    pub includes: Vec<Spanned<Include>>,
+    pub balance_forward: Option<Spanned<BalanceForward>>,
+    /// Each span covers the heading of the contract, leaving out its days
+    pub contracts: Vec<Spanned<Contract>>,
+    pub allowances: Vec<Spanned<Allowance>>,
+    pub weeks: Vec<Spanned<Week, WeekSpans>>,
+}
+
+impl Timesheets {
+    /// The AST alone, without the spans
+    pub fn into_weeks(self) -> Weeks {
+        let Timesheets { includes, balance_forward, contracts, allowances, weeks } = self;
+
+        Weeks(
+            includes.into_iter().map(|include| include.node).collect(),
+            balance_forward.map(|balance_forward| balance_forward.node),
+            contracts.into_iter().map(|contract| contract.node).collect(),
+            allowances.into_iter().map(|allowance| allowance.node).collect(),
+            weeks.into_iter().map(|week| week.node).collect(),
+        )
+    }
+}
 
 #[derive(Debug)]
 pub enum ParsingError {
@@ -27,20 +63,52 @@ impl std::fmt::Display for ParsingError {
     }
 }
 
-pub fn parse_timesheets(code: &str) -> Result<Weeks, ParsingError> {
+pub fn parse_timesheets(code: &str) -> Result<Timesheets, ParsingError> {
     let pairs = parse(code)
         .map_err(ParsingError::PestError)?;
 
-    pack(pairs)
-        .map_err(ParsingError::PackingError)
+    let mut timesheets = Timesheets { includes: vec![], balance_forward: None, contracts: vec![], allowances: vec![], weeks: vec![] };
+
+    let parts = pairs.flatten()
+        .filter(|pair| pair.as_rule() == Rule::weeks)
+        .flat_map(|weeks| weeks.into_inner());
+
+    for pair in parts {
+        match pair.as_rule() {
+            Rule::include => timesheets.includes.push(pack_spanned(pair, |pair| pair.as_span().into())?),
+            Rule::balance_forward => timesheets.balance_forward = Some(pack_spanned(pair, |pair| pair.as_span().into())?),
+            Rule::contract => timesheets.contracts.push(pack_spanned(pair, heading_span)?),
+            Rule::allowance => timesheets.allowances.push(pack_spanned(pair, |pair| pair.as_span().into())?),
+            Rule::week => timesheets.weeks.push(pack_spanned(pair, |pair| WeekSpans::from(pair.clone()))?),
+            _ => {},
+        }
+    }
+
+    Ok(timesheets)
 }
 
-pub fn pack(pairs: Pairs<'_, Rule>) -> Result<Weeks, PackingError<Rule>> {
-    let trees: Vec<_> = pairs.map(SyntaxTree::from)
-        .collect();
+fn pack_spanned<T, S>(pair: Pair<'_, Rule>, spans: fn(&Pair<'_, Rule>) -> S) -> Result<Spanned<T, S>, ParsingError>
+where
+    T: TokenPacker + HasRule<Rule = Rule>,
+{
+    let spans = spans(&pair);
+    let node = T::pack(&SyntaxTree::from(pair))
+        .map_err(ParsingError::PackingError)?;
 
-    unpack_only_tree(&trees)
-        .and_then(Body::pack)
-        .map(|body| body.0)
+    Ok(Spanned { node, spans })
 }
 
+
+
+#[test]
+fn check_days_are_only_for_time_off() {
+    let day = |log: &str| parse_timesheets(&format!("WEEK 2024-09-16\n  Monday\n    {log}\n"));
+
+    for log in ["LEAVE 1d", "SICK 0.5d | Flu", "HOLIDAY HALF PM", "TOIL HALF AM", "WORKING DAY 7h 30m"] {
+        assert!(day(log).is_ok(), "{log}");
+    }
+
+    for log in ["WORKING DAY 1d", "WORK 0.5d | Release", "LUNCH HALF PM", "BREAK 1d | Errand"] {
+        assert!(day(log).is_err(), "{log}");
+    }
+}
@@ -1,88 +1,164 @@
 use std::ops::Deref;
 
-use chrono::NaiveTime;
+use chrono::{Datelike, Month, NaiveDate, NaiveTime, Weekday};
+use itertools::Itertools;
 use lang_packer::Packer;
 
 use crate::parser::Rule;
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::MONTH_NAME)]
+pub struct MonthName(pub Month);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::DAY_OF_MONTH)]
+pub struct DayOfMonth(pub Number);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::date_long)]
+pub struct LongDate(pub DayOfMonth, pub MonthName, pub Number);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::DATE_ISO)]
+pub struct IsoDate(pub Number, pub Number, pub Number);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::DATE_ISO_WEEK)]
+pub struct IsoWeekDate(pub Number, pub Number);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::DATE)]
-pub struct Date(pub String);
+pub enum Date {
+    IsoWeek(IsoWeekDate),
+    Iso(IsoDate),
+    Long(LongDate),
+}
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, Copy, PartialEq, Packer)]
 #[packer(rule = Rule::DAY_NAME)]
-pub struct DayName(pub String);
+pub struct DayName(pub Weekday);
 
-#[derive(Debug, Clone, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::TIME)]
 pub struct Time(pub NaiveTime);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::numbers)]
 pub struct Number(pub i64);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::PERIOD_MINUTES)]
 pub struct Minutes(pub Number);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::PERIOD_HOURS)]
 pub struct Hours(pub Number);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::period_hours_minutes)]
 pub struct HoursMinutes(pub Hours, pub Option<Minutes>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::DECIMAL)]
+pub struct Decimal(pub f64);
+
+/// A number of days, each as long as the day's expected hours
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::PERIOD_DAYS)]
+pub struct DayCount(pub Decimal);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::AM)]
+pub struct Am;
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::PM)]
+pub struct Pm;
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::half_of_day)]
+pub enum HalfOfDay {
+    Morning(Am),
+    Afternoon(Pm),
+}
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::half_day)]
+pub struct HalfDay(pub HalfOfDay);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::PERIOD)]
 pub enum Period {
     Minutes(Minutes),
     HoursMinutes(HoursMinutes),
+    Days(DayCount),
+    HalfDay(HalfDay),
 }
 
-#[derive(Debug, Clone, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::NOW)]
 pub struct Now;
 
-#[derive(Debug, Clone, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::TIME_NEXT_DAY)]
+pub struct NextDayTime(pub Time);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::time_range_end)]
 pub enum TimeRangeEnd {
     Now(Now),
+    NextDay(NextDayTime),
     Time(Time),
 }
 
-#[derive(Debug, Clone, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
 #[packer(rule = Rule::time_range)]
 pub struct TimeRange(pub Time, pub TimeRangeEnd);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::time_period)]
 pub enum TimePeriod {
     Period(Period),
     TimeRange(TimeRange),
 }
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::SUMMARY)]
+pub struct Summary(pub String);
+
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::work)]
-pub struct WorkLog(pub TimePeriod);
+pub struct WorkLog(pub TimePeriod, pub Option<Summary>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::working_day)]
-pub struct WorkingDayLog(pub TimePeriod);
+pub struct WorkingDayLog(pub TimePeriod, pub Option<Summary>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::lunch)]
-pub struct LunchLog(pub TimePeriod);
+pub struct LunchLog(pub TimePeriod, pub Option<Summary>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::r#break)]
-pub struct BreakLog(pub TimePeriod);
+pub struct BreakLog(pub TimePeriod, pub Option<Summary>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::leave)]
-pub struct LeaveLog(pub TimePeriod);
+pub struct LeaveLog(pub TimePeriod, pub Option<Summary>);
+
+#[derive(Debug, PartialEq, Packer)]
+#[packer(rule = Rule::sick)]
+pub struct SickLog(pub Option<TimePeriod>, pub Option<Summary>);
+
+#[derive(Debug, PartialEq, Packer)]
+#[packer(rule = Rule::holiday)]
+pub struct HolidayLog(pub Option<TimePeriod>, pub Option<Summary>);
+
+#[derive(Debug, PartialEq, Packer)]
+#[packer(rule = Rule::toil)]
+pub struct ToilLog(pub Option<TimePeriod>, pub Option<Summary>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::log_event)]
 pub enum LogEvent {
     Work(WorkLog),
@@ -90,35 +166,69 @@ pub enum LogEvent {
     Lunch(LunchLog),
     Break(BreakLog),
     Leave(LeaveLog),
+    Sick(SickLog),
+    Holiday(HolidayLog),
+    Toil(ToilLog),
 }
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::log)]
 pub struct Log(pub LogEvent);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::day)]
 pub struct Day(pub DayName, pub Vec<Log>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::days)]
 pub struct Days(pub Vec<Day>);
 
-#[derive(Debug, Packer)]
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::NEGATIVE)]
+pub struct Negative;
+
+#[derive(Debug, PartialEq, Packer)]
+#[packer(rule = Rule::balance_forward)]
+pub struct BalanceForward(pub Option<Negative>, pub Period, pub Option<Summary>);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::contract_day)]
+pub struct ContractDay(pub DayName, pub Period);
+
+/// The time expected on each day of the week from the given date, with nothing expected on the days left out
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::contract)]
+pub struct Contract(pub Date, pub Option<Summary>, pub Vec<ContractDay>);
+
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::expiry)]
+pub struct Expiry(pub Date);
+
+/// Days left over from the year before, which may have to be used by a certain date
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::carry_over)]
+pub struct CarryOver(pub DayCount, pub Option<Expiry>);
+
+/// The days of leave for the year starting on the given date, which runs until the next allowance starts
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::allowance)]
+pub struct Allowance(pub DayCount, pub Date, pub Option<CarryOver>, pub Option<Summary>);
+
+#[derive(Debug, PartialEq, Packer)]
 #[packer(rule = Rule::week)]
-pub struct Week(pub Date, pub Days);
+pub struct Week(pub Date, pub Option<BalanceForward>, pub Days);
 
-#[derive(Debug, Packer)]
-#[packer(rule = Rule::weeks)]
-pub struct Weeks(pub Vec<Week>);
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::INCLUDE_PATH)]
+pub struct IncludePath(pub String);
 
-#[derive(Debug, Packer)]
-#[packer(rule = Rule::EOI)]
-pub struct EOI;
+#[derive(Debug, Clone, PartialEq, Packer)]
+#[packer(rule = Rule::include)]
+pub struct Include(pub IncludePath);
 
-#[derive(Debug, Packer)]
-#[packer(rule = Rule::body)]
-pub struct Body(pub Weeks, pub EOI);
+#[derive(Debug, PartialEq, Packer)]
+#[packer(rule = Rule::weeks)]
+pub struct Weeks(pub Vec<Include>, pub Option<BalanceForward>, pub Vec<Contract>, pub Vec<Allowance>, pub Vec<Week>);
 
 
 
@@ -150,10 +260,100 @@ impl std::fmt::Display for Period {
 
             Period::HoursMinutes(HoursMinutes(Hours(Number(hours)), Some(Minutes(Number(minutes))))) =>
                 f.write_fmt(format_args!("{hours}h {minutes}m")),
+
+            Period::Days(DayCount(Decimal(days))) =>
+                f.write_fmt(format_args!("{days}d")),
+
+            Period::HalfDay(HalfDay(HalfOfDay::Morning(_))) =>
+                f.write_str("HALF AM"),
+
+            Period::HalfDay(HalfDay(HalfOfDay::Afternoon(_))) =>
+                f.write_str("HALF PM"),
+        }
+    }
+}
+
+impl Date {
+    /// `None` if the date doesn't exist, e.g. 31st February
+    pub fn to_naive_date(&self) -> Option<NaiveDate> {
+        fn to_u32(Number(n): &Number) -> Option<u32> {
+            u32::try_from(*n).ok()
+        }
+
+        fn to_i32(Number(n): &Number) -> Option<i32> {
+            i32::try_from(*n).ok()
+        }
+
+        match self {
+            Date::IsoWeek(IsoWeekDate(year, week)) =>
+                NaiveDate::from_isoywd_opt(to_i32(year)?, to_u32(week)?, Weekday::Mon),
+
+            Date::Iso(IsoDate(year, month, day)) =>
+                NaiveDate::from_ymd_opt(to_i32(year)?, to_u32(month)?, to_u32(day)?),
+
+            Date::Long(LongDate(DayOfMonth(day), MonthName(month), year)) =>
+                NaiveDate::from_ymd_opt(to_i32(year)?, month.number_from_month(), to_u32(day)?),
+        }
+    }
+}
+
+impl From<NaiveDate> for LongDate {
+    fn from(date: NaiveDate) -> Self {
+        let month = Month::try_from(date.month() as u8)
+            .expect("NaiveDate always holds a valid month");
+
+        LongDate(
+            DayOfMonth(Number(date.day().into())),
+            MonthName(month),
+            Number(date.year().into()),
+        )
+    }
+}
+
+impl std::fmt::Display for LongDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let LongDate(DayOfMonth(Number(day)), MonthName(month), Number(year)) = self;
+
+        let suffix = match (day % 10, day % 100) {
+            (_, 11..=13) => "th",
+            (1, _) => "st",
+            (2, _) => "nd",
+            (3, _) => "rd",
+            _ => "th",
+        };
+
+        f.write_fmt(format_args!("{day}{suffix} {} {year}", month.name()))
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Date::IsoWeek(IsoWeekDate(Number(year), Number(week))) =>
+                f.write_fmt(format_args!("{year}-W{week:0>2}")),
+
+            Date::Iso(IsoDate(Number(year), Number(month), Number(day))) =>
+                f.write_fmt(format_args!("{year}-{month:0>2}-{day:0>2}")),
+
+            Date::Long(long_date) => long_date.fmt(f),
         }
     }
 }
 
+impl std::fmt::Display for DayName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self.0 {
+            Weekday::Mon => "Monday",
+            Weekday::Tue => "Tuesday",
+            Weekday::Wed => "Wednesday",
+            Weekday::Thu => "Thursday",
+            Weekday::Fri => "Friday",
+            Weekday::Sat => "Saturday",
+            Weekday::Sun => "Sunday",
+        })
+    }
+}
+
 impl std::fmt::Display for Time {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!("{}", self.0.format("%H:%M")))
@@ -164,6 +364,7 @@ impl std::fmt::Display for TimeRangeEnd {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TimeRangeEnd::Now(_) => f.write_str("NOW"),
+            TimeRangeEnd::NextDay(NextDayTime(time)) => f.write_fmt(format_args!("{time}+1")),
             TimeRangeEnd::Time(time) => f.write_fmt(format_args!("{time}")),
         }
     }
@@ -186,12 +387,307 @@ impl std::fmt::Display for TimePeriod {
     }
 }
 
+impl std::fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogEvent::Work(log) => log.fmt(f),
+            LogEvent::WorkingDay(log) => log.fmt(f),
+            LogEvent::Lunch(log) => log.fmt(f),
+            LogEvent::Break(log) => log.fmt(f),
+            LogEvent::Leave(log) => log.fmt(f),
+            LogEvent::Sick(log) => log.fmt(f),
+            LogEvent::Holiday(log) => log.fmt(f),
+            LogEvent::Toil(log) => log.fmt(f),
+        }
+    }
+}
+
+impl std::fmt::Display for Log {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Log(event) = self;
+
+        match event.summary() {
+            Some(summary) => f.write_fmt(format_args!("{event} | {summary}")),
+            None => f.write_fmt(format_args!("{event}")),
+        }
+    }
+}
+
+impl LogEvent {
+    /// `None` for a day off given without one, which takes up the whole day
+    pub fn time_period(&self) -> Option<&TimePeriod> {
+        match self {
+            LogEvent::Work(WorkLog(period, _))
+            | LogEvent::WorkingDay(WorkingDayLog(period, _))
+            | LogEvent::Lunch(LunchLog(period, _))
+            | LogEvent::Break(BreakLog(period, _))
+            | LogEvent::Leave(LeaveLog(period, _)) => Some(period),
+
+            LogEvent::Sick(SickLog(period, _))
+            | LogEvent::Holiday(HolidayLog(period, _))
+            | LogEvent::Toil(ToilLog(period, _)) => period.as_ref(),
+        }
+    }
+
+    /// The time period and commentary, for rewriting in place
+    pub fn parts_mut(&mut self) -> (Option<&mut TimePeriod>, &mut Option<Summary>) {
+        match self {
+            LogEvent::Work(WorkLog(period, summary))
+            | LogEvent::WorkingDay(WorkingDayLog(period, summary))
+            | LogEvent::Lunch(LunchLog(period, summary))
+            | LogEvent::Break(BreakLog(period, summary))
+            | LogEvent::Leave(LeaveLog(period, summary)) => (Some(period), summary),
+
+            LogEvent::Sick(SickLog(period, summary))
+            | LogEvent::Holiday(HolidayLog(period, summary))
+            | LogEvent::Toil(ToilLog(period, summary)) => (period.as_mut(), summary),
+        }
+    }
+
+    /// The commentary following `|`, if any
+    pub fn summary(&self) -> Option<&str> {
+        let summary = match self {
+            LogEvent::Work(WorkLog(_, summary))
+            | LogEvent::WorkingDay(WorkingDayLog(_, summary))
+            | LogEvent::Lunch(LunchLog(_, summary))
+            | LogEvent::Break(BreakLog(_, summary))
+            | LogEvent::Leave(LeaveLog(_, summary))
+            | LogEvent::Sick(SickLog(_, summary))
+            | LogEvent::Holiday(HolidayLog(_, summary))
+            | LogEvent::Toil(ToilLog(_, summary)) => summary,
+        };
+
+        summary.as_ref()
+            .map(|Summary(summary)| summary.trim())
+    }
+}
+
+/*
+ * Each log event displays as it's written in source, without its commentary,
+ * which is left to `Log` and `Day` so that it can be aligned
+ */
+
+impl std::fmt::Display for WorkLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let WorkLog(period, _) = self;
+
+        f.write_fmt(format_args!("WORK {period}"))
+    }
+}
+
 impl std::fmt::Display for WorkingDayLog {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let WorkingDayLog(period) = self;
+        let WorkingDayLog(period, _) = self;
+
+        f.write_fmt(format_args!("WORKING DAY {period}"))
+    }
+}
+
+impl std::fmt::Display for LunchLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let LunchLog(period, _) = self;
+
+        f.write_fmt(format_args!("LUNCH {period}"))
+    }
+}
+
+impl std::fmt::Display for BreakLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let BreakLog(period, _) = self;
+
+        f.write_fmt(format_args!("BREAK {period}"))
+    }
+}
+
+impl std::fmt::Display for LeaveLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let LeaveLog(period, _) = self;
+
+        f.write_fmt(format_args!("LEAVE {period}"))
+    }
+}
+
+/// A day off given without a time period is written as its keyword alone
+fn write_day_off(f: &mut std::fmt::Formatter<'_>, keyword: &str, period: &Option<TimePeriod>) -> std::fmt::Result {
+    match period {
+        Some(period) => f.write_fmt(format_args!("{keyword} {period}")),
+        None => f.write_str(keyword),
+    }
+}
+
+impl std::fmt::Display for SickLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let SickLog(period, _) = self;
 
-        f.write_fmt(format_args!("    WORKING DAY {period}"))?;
+        write_day_off(f, "SICK", period)
+    }
+}
+
+impl std::fmt::Display for HolidayLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let HolidayLog(period, _) = self;
+
+        write_day_off(f, "HOLIDAY", period)
+    }
+}
+
+impl std::fmt::Display for ToilLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ToilLog(period, _) = self;
+
+        write_day_off(f, "TOIL", period)
+    }
+}
+
+/// Logs are indented beneath their day, with their commentary lined up
+impl std::fmt::Display for Day {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Day(day_name, logs) = self;
+
+        let events: Vec<_> = logs.iter()
+            .map(|Log(event)| (event.to_string(), event.summary()))
+            .collect();
+
+        let width = events.iter()
+            .filter(|(_, summary)| summary.is_some())
+            .map(|(event, _)| event.chars().count())
+            .max()
+            .unwrap_or_default();
+
+        f.write_fmt(format_args!("  {day_name}\n"))?;
+
+        for (event, summary) in events {
+            match summary {
+                Some(summary) => f.write_fmt(format_args!("    {event:<width$} | {summary}\n"))?,
+                None => f.write_fmt(format_args!("    {event}\n"))?,
+            }
+        }
 
         Ok(())
-   }
+    }
+}
+
+/// Always signed, so that a credit can't be mistaken for a deficit
+impl std::fmt::Display for BalanceForward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let BalanceForward(negative, period, summary) = self;
+
+        let sign = match negative {
+            Some(Negative) => '-',
+            None => '+',
+        };
+
+        match summary {
+            Some(Summary(summary)) => f.write_fmt(format_args!("BALANCE FORWARD {sign}{period} | {}", summary.trim())),
+            None => f.write_fmt(format_args!("BALANCE FORWARD {sign}{period}")),
+        }
+    }
+}
+
+/// The heading alone, which is enough to tell one contract from another
+impl std::fmt::Display for Contract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Contract(date, summary, _) = self;
+
+        match summary {
+            Some(Summary(summary)) => f.write_fmt(format_args!("CONTRACT FROM {date} | {}", summary.trim())),
+            None => f.write_fmt(format_args!("CONTRACT FROM {date}")),
+        }
+    }
+}
+
+impl Contract {
+    /// The heading followed by a day to a line, as written in the timesheet
+    pub fn to_block(&self) -> String {
+        let Contract(_, _, days) = self;
+
+        let days = days.iter()
+            .map(|ContractDay(day_name, period)| format!("  {day_name} {period}\n"))
+            .join("");
+
+        format!("{self}\n{days}")
+    }
+}
+
+impl std::fmt::Display for Allowance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Allowance(DayCount(Decimal(days)), date, carry_over, summary) = self;
+
+        f.write_fmt(format_args!("ALLOWANCE {days}d FROM {date}"))?;
+
+        match carry_over {
+            Some(CarryOver(DayCount(Decimal(days)), Some(Expiry(expiry)))) => f.write_fmt(format_args!(" CARRY OVER {days}d UNTIL {expiry}"))?,
+            Some(CarryOver(DayCount(Decimal(days)), None)) => f.write_fmt(format_args!(" CARRY OVER {days}d"))?,
+            None => {},
+        }
+
+        match summary {
+            Some(Summary(summary)) => f.write_fmt(format_args!(" | {}", summary.trim())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Days are separated by a blank line, with any balance forward directly beneath the heading
+impl std::fmt::Display for Week {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Week(date, balance_forward, Days(days)) = self;
+
+        f.write_fmt(format_args!("WEEK {date}\n"))?;
+
+        if let Some(balance_forward) = balance_forward {
+            f.write_fmt(format_args!("  {balance_forward}\n"))?;
+        }
+
+        f.write_str(&days.iter().map(Day::to_string).join("\n"))
+    }
+}
+
+impl std::fmt::Display for Include {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Include(IncludePath(path)) = self;
+
+        f.write_fmt(format_args!("INCLUDE \"{path}\""))
+    }
+}
+
+/// Weeks are separated by a blank line, as are the includes, balance forward, contracts and allowances above them.
+/// The includes and allowances are each kept together, one to a line
+impl std::fmt::Display for Weeks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Weeks(includes, balance_forward, contracts, allowances, weeks) = self;
+
+        let includes = (!includes.is_empty())
+            .then(|| includes.iter().map(|include| format!("{include}\n")).join(""));
+
+        let balance_forward = balance_forward.iter()
+            .map(|balance_forward| format!("{balance_forward}\n"));
+
+        let allowances = (!allowances.is_empty())
+            .then(|| allowances.iter().map(|allowance| format!("{allowance}\n")).join(""));
+
+        let contracts = contracts.iter()
+            .map(Contract::to_block);
+
+        f.write_str(&includes.into_iter().chain(balance_forward).chain(contracts).chain(allowances).chain(weeks.iter().map(Week::to_string)).join("\n"))
+    }
+}
+
+impl Period {
+    /// The same length of time, with minutes past the hour carried into hours, e.g. `90m` as `1h 30m`.
+    /// Days and half days are left as they are
+    pub fn normalised(&self) -> Period {
+        let minutes = match self {
+            Period::Minutes(Minutes(Number(minutes))) => *minutes,
+            Period::HoursMinutes(HoursMinutes(Hours(Number(hours)), minutes)) =>
+                hours * 60 + minutes.as_ref().map_or(0, |Minutes(Number(minutes))| *minutes),
+            Period::Days(_) | Period::HalfDay(_) => return self.clone(),
+        };
+
+        match (minutes / 60, minutes % 60) {
+            (0, minutes) => Period::Minutes(Minutes(Number(minutes))),
+            (hours, 0) => Period::HoursMinutes(HoursMinutes(Hours(Number(hours)), None)),
+            (hours, minutes) => Period::HoursMinutes(HoursMinutes(Hours(Number(hours)), Some(Minutes(Number(minutes))))),
+        }
+    }
 }
@@ -14,6 +14,6 @@ pub fn parse(code: &str) -> Result<Pairs<'_, Rule>, Box<::pest::error::Error<Rul
 
 impl DropRules for Rule {
     fn get_drop_rules(&self) -> Vec<Self> {
-        vec![ Rule::TAB, Rule::SUMMARY ]
+        vec![ Rule::TAB ]
     }
 }
@@ -0,0 +1,165 @@
+use pest::iterators::Pair;
+
+use crate::parser::{Rule, Spanned};
+
+/// A stretch of source code, by byte offset, along with the line and column it starts at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    /// Which of the loaded files it's in, with the file given on the command line being 0
+    pub file: usize,
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        let (line, column) = span.start_pos().line_col();
+
+        Span {
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+            file: 0,
+        }
+    }
+}
+
+/*
+ * The packed AST doesn't know where it came from, so the spans are kept alongside it instead, in a `Spanned`.
+ * Each of these mirrors the node of the same name, with its children in the same order,
+ * such that e.g. the spans of `week.node.2.0[2]` are found at `week.spans.days[2]`
+ */
+
+#[derive(Debug, Clone)]
+pub struct WeekSpans {
+    pub span: Span,
+    pub date: Span,
+    pub days: Vec<DaySpans>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DaySpans {
+    pub span: Span,
+    pub day_name: Span,
+    pub logs: Vec<LogSpans>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogSpans {
+    /// Covers the log itself, without its indentation or line ending
+    pub span: Span,
+    pub time_period: Span,
+    pub time_range_end: Option<Span>,
+}
+
+fn find_span(pair: &Pair<'_, Rule>, rule: Rule) -> Option<Span> {
+    pair.clone()
+        .into_inner()
+        .flatten()
+        .find(|inner| inner.as_rule() == rule)
+        .map(|inner| inner.as_span().into())
+}
+
+fn find_all<'i>(pair: &Pair<'i, Rule>, rule: Rule) -> impl Iterator<Item = Pair<'i, Rule>> {
+    pair.clone()
+        .into_inner()
+        .flatten()
+        .filter(move |inner| inner.as_rule() == rule)
+}
+
+impl From<Pair<'_, Rule>> for LogSpans {
+    fn from(pair: Pair<'_, Rule>) -> Self {
+        let span = find_span(&pair, Rule::log_event)
+            .unwrap_or_else(|| pair.as_span().into());
+
+        LogSpans {
+            span,
+            time_period: find_span(&pair, Rule::time_period).unwrap_or(span),
+            time_range_end: find_span(&pair, Rule::time_range_end),
+        }
+    }
+}
+
+impl From<Pair<'_, Rule>> for DaySpans {
+    fn from(pair: Pair<'_, Rule>) -> Self {
+        let span = pair.as_span().into();
+
+        DaySpans {
+            span,
+            day_name: find_span(&pair, Rule::DAY_NAME).unwrap_or(span),
+            logs: find_all(&pair, Rule::log).map(LogSpans::from).collect(),
+        }
+    }
+}
+
+impl From<Pair<'_, Rule>> for WeekSpans {
+    fn from(pair: Pair<'_, Rule>) -> Self {
+        let span = pair.as_span().into();
+
+        WeekSpans {
+            span,
+            date: find_span(&pair, Rule::DATE).unwrap_or(span),
+            days: find_all(&pair, Rule::day).map(DaySpans::from).collect(),
+        }
+    }
+}
+
+/// The first line of the pair, such as the heading of a contract without its days
+pub fn heading_span(pair: &Pair<'_, Rule>) -> Span {
+    let span = Span::from(pair.as_span());
+    let heading = pair.as_str().find(['\r', '\n']).unwrap_or(span.end - span.start);
+
+    Span { end: span.start + heading, ..span }
+}
+
+impl Span {
+    pub fn in_file(self, file: usize) -> Span {
+        Span { file, ..self }
+    }
+}
+
+impl LogSpans {
+    fn in_file(self, file: usize) -> Self {
+        LogSpans {
+            span: self.span.in_file(file),
+            time_period: self.time_period.in_file(file),
+            time_range_end: self.time_range_end.map(|span| span.in_file(file)),
+        }
+    }
+}
+
+impl DaySpans {
+    fn in_file(self, file: usize) -> Self {
+        DaySpans {
+            span: self.span.in_file(file),
+            day_name: self.day_name.in_file(file),
+            logs: self.logs.into_iter().map(|logs| logs.in_file(file)).collect(),
+        }
+    }
+}
+
+impl WeekSpans {
+    /// The same spans, found in another of the loaded files
+    pub fn in_file(self, file: usize) -> Self {
+        WeekSpans {
+            span: self.span.in_file(file),
+            date: self.date.in_file(file),
+            days: self.days.into_iter().map(|days| days.in_file(file)).collect(),
+        }
+    }
+}
+
+impl<T> Spanned<T> {
+    pub fn in_file(self, file: usize) -> Self {
+        Spanned { spans: self.spans.in_file(file), ..self }
+    }
+}
+
+impl<T> Spanned<T, WeekSpans> {
+    pub fn in_file(self, file: usize) -> Self {
+        Spanned { spans: self.spans.in_file(file), ..self }
+    }
+}
@@ -0,0 +1,129 @@
+/*
+ * Code frames for problems found in a timesheet, in the style of rustc:
+ *
+ *  error: Monday appears twice in the same week
+ *    --> my-timesheet.tsh:12:3
+ *     |
+ *  12 |   Monday
+ *     |   ^^^^^^
+ *     = hint: move these logs under the earlier Monday, on line 2
+ */
+
+use std::{fmt::Display, path::Path};
+
+use crate::{loader::Source, parser::Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub hint: Option<String>,
+}
+
+/// Every diagnostic found in a file and those it includes, in the order they appear in them
+#[derive(Debug)]
+pub struct FileDiagnostics {
+    /// Indexed by the file of each span, starting with the file given on the command line
+    pub sources: Vec<Source>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A diagnostic, alongside the source it refers to
+pub struct CodeFrame<'a> {
+    pub diagnostic: &'a Diagnostic,
+    pub path: &'a Path,
+    pub code: &'a str,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+impl Display for CodeFrame<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let CodeFrame { diagnostic: Diagnostic { severity, message, span, hint }, path, code } = self;
+
+        let line = code.lines()
+            .nth(span.line.saturating_sub(1))
+            .unwrap_or_default();
+
+        // Spans running over several lines are only underlined up to the end of the first
+        let underline_length = code.get(span.start..span.end)
+            .and_then(|spanned| spanned.lines().next())
+            .map(|spanned| spanned.chars().count())
+            .unwrap_or_default()
+            .max(1);
+
+        let line_number = span.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        f.write_fmt(format_args!("{severity}: {message}\n"))?;
+        f.write_fmt(format_args!("{gutter}--> {}:{}:{}\n", path.display(), span.line, span.column))?;
+        f.write_fmt(format_args!("{gutter} |\n"))?;
+        f.write_fmt(format_args!("{line_number} | {line}\n"))?;
+        f.write_fmt(format_args!("{gutter} | {}{}\n", " ".repeat(span.column.saturating_sub(1)), "^".repeat(underline_length)))?;
+
+        if let Some(hint) = hint {
+            f.write_fmt(format_args!("{gutter} = hint: {hint}\n"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FileDiagnostics {
+    pub fn new(sources: Vec<Source>, mut diagnostics: Vec<Diagnostic>) -> Self {
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.span.file, diagnostic.span.start));
+
+        FileDiagnostics { sources, diagnostics }
+    }
+
+    /// The file given on the command line
+    pub fn path(&self) -> &Path {
+        &self.sources[0].path
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.diagnostics.iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+}
+
+impl Display for FileDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let FileDiagnostics { sources, diagnostics } = self;
+
+        for diagnostic in diagnostics {
+            let Source { path, code } = &sources[diagnostic.span.file];
+
+            f.write_fmt(format_args!("{}\n", CodeFrame { diagnostic, path, code }))?;
+        }
+
+        let found_in = match sources.len() {
+            1 => self.path().display().to_string(),
+            _ => format!("{} and the files it includes", self.path().display()),
+        };
+
+        match self.count(Severity::Warning) {
+            0 => f.write_fmt(format_args!("{} error(s) found in {found_in}", self.count(Severity::Error))),
+            warnings => f.write_fmt(format_args!("{} error(s) and {warnings} warning(s) found in {found_in}", self.count(Severity::Error))),
+        }
+    }
+}
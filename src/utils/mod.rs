@@ -1,3 +1,3 @@
+pub mod diagnostic;
+pub mod table_render;
 pub mod term_render;
-
-
@@ -0,0 +1,90 @@
+/*
+ * Plain tables, for pasting elsewhere rather than reading in a terminal.
+ * The same table can be rendered as CSV for spreadsheets, or as Markdown for emails and notes
+ */
+
+use std::{fmt::Display, iter::once};
+
+use itertools::Itertools;
+
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub struct Csv<'a>(pub &'a Table);
+pub struct Markdown<'a>(pub &'a Table);
+
+/// Quoted only when it has to be, as per RFC 4180
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned(),
+    }
+}
+
+fn markdown_field(field: &str) -> String {
+    field.replace('|', "\\|")
+        .replace('\n', " ")
+}
+
+impl Display for Csv<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Csv(Table { headers, rows }) = self;
+
+        for row in once(headers).chain(rows) {
+            f.write_fmt(format_args!("{}\r\n", row.iter().map(|field| csv_field(field)).join(",")))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Markdown<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Markdown(Table { headers, rows }) = self;
+
+        let widths: Vec<_> = headers.iter()
+            .enumerate()
+            .map(|(i, header)| rows.iter()
+                .filter_map(|row| row.get(i))
+                .chain(once(header))
+                .map(|field| markdown_field(field).chars().count())
+                .max()
+                .unwrap_or_default()
+                .max(3))
+            .collect();
+
+        let write_row = |f: &mut std::fmt::Formatter<'_>, row: &[String]| {
+            let fields = row.iter()
+                .zip(&widths)
+                .map(|(field, width)| format!("{:<width$}", markdown_field(field)))
+                .join(" | ");
+
+            f.write_fmt(format_args!("| {fields} |\n"))
+        };
+
+        write_row(f, headers)?;
+        write_row(f, &widths.iter().map(|width| "-".repeat(*width)).collect_vec())?;
+
+        for row in rows {
+            write_row(f, row)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn check_fields_are_escaped() {
+    let table = Table {
+        headers: vec!["log".into(), "commentary".into()],
+        rows: vec![vec!["BREAK 15m".into(), "Dentist, \"urgent\" | again".into()]],
+    };
+
+    assert_eq!(Csv(&table).to_string(), "log,commentary\r\nBREAK 15m,\"Dentist, \"\"urgent\"\" | again\"\r\n");
+    assert_eq!(
+        Markdown(&table).to_string(),
+        "| log       | commentary                 |\n| --------- | -------------------------- |\n| BREAK 15m | Dentist, \"urgent\" \\| again |\n"
+    );
+}
@@ -25,15 +25,144 @@ use std::{fmt::Display, iter::once};
 pub struct Cell {
     pub figure: String,
     pub comment: String,
-} 
+    pub tone: Tone,
+}
+
+/// What a cell means, which decides how it's coloured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Neutral,
+    Credit,
+    Deficit,
+    Heading,
+    /// A heading whose figure is a credit or deficit of its own, such as the week's total
+    CreditHeading,
+    DeficitHeading,
+    Warning,
+}
 
 pub struct Segment(pub Vec<Cell>);
 pub struct Block(pub Vec<Segment>); 
 pub struct Column(pub Vec<Block>);
 
 
-impl Display for Block {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// How a column is drawn, besides what's in it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Style {
+    /// Draw boxes from `+`, `-` and `|`, for terminals without box-drawing glyphs
+    pub ascii: bool,
+    /// Wrap comments so that lines fit within this many characters
+    pub width: Option<usize>,
+    /// Colour cells by their tone, with ANSI escape codes
+    pub color: bool,
+}
+
+/// Something rendered with a style other than the default
+pub struct Styled<'a, T>(pub &'a T, pub Style);
+
+struct Glyphs {
+    top: [char; 3],
+    divider: [char; 3],
+    bottom: [char; 3],
+    vertical: char,
+}
+
+const BOX_DRAWING: Glyphs = Glyphs {
+    top: ['┌', '─', '┐'],
+    divider: ['├', '─', '┤'],
+    bottom: ['└', '─', '┘'],
+    vertical: '│',
+};
+
+const ASCII: Glyphs = Glyphs {
+    top: ['+', '-', '+'],
+    divider: ['+', '-', '+'],
+    bottom: ['+', '-', '+'],
+    vertical: '|',
+};
+
+// Fits the figures used throughout, e.g. "+1:30", so that columns don't shift about from one run to the next
+const MIN_FIGURE_WIDTH: usize = 5;
+
+// Comments are never squeezed narrower than this, so that a narrow terminal doesn't leave a word per line
+const MIN_COMMENT_WIDTH: usize = 20;
+
+impl Style {
+    /// The default style, wrapped to the width of the terminal if there is one
+    pub fn for_terminal(ascii: bool, color: bool) -> Self {
+        Style {
+            ascii,
+            color,
+            width: terminal_size::terminal_size()
+                .map(|(terminal_size::Width(width), _)| width.into()),
+        }
+    }
+
+    fn glyphs(&self) -> &'static Glyphs {
+        match self.ascii {
+            true => &ASCII,
+            false => &BOX_DRAWING,
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+
+impl Tone {
+    /// The ANSI escape codes for the figure and the comment
+    fn colors(&self) -> (Option<&'static str>, Option<&'static str>) {
+        match self {
+            Tone::Neutral => (None, None),
+            Tone::Credit => (Some("\x1b[32m"), None),
+            Tone::Deficit => (Some("\x1b[31m"), None),
+            Tone::Heading => (Some("\x1b[1m"), Some("\x1b[1m")),
+            Tone::CreditHeading => (Some("\x1b[1;32m"), Some("\x1b[1m")),
+            Tone::DeficitHeading => (Some("\x1b[1;31m"), Some("\x1b[1m")),
+            Tone::Warning => (Some("\x1b[33m"), Some("\x1b[33m")),
+        }
+    }
+}
+
+fn paint(text: &str, color: Option<&str>) -> String {
+    match color {
+        Some(color) => format!("{color}{text}{RESET}"),
+        None => text.to_owned(),
+    }
+}
+
+/// Breaks between words, with any word too long for a line of its own left to overflow
+fn wrap(comment: &str, width: Option<usize>) -> Vec<String> {
+    let Some(width) = width else {
+        return vec![comment.to_owned()];
+    };
+
+    let mut lines: Vec<String> = vec![];
+
+    for word in comment.split(' ') {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            },
+
+            _ => lines.push(word.to_owned()),
+        }
+    }
+
+    lines
+}
+
+impl Block {
+    fn figure_width(&self) -> usize {
+        self.0.iter()
+            .flat_map(|segment| &segment.0)
+            .map(|cell| cell.figure.chars().count())
+            .max()
+            .unwrap_or_default()
+            .max(MIN_FIGURE_WIDTH)
+    }
+
+    fn render(&self, f: &mut std::fmt::Formatter<'_>, style: &Style, figure_width: usize) -> std::fmt::Result {
         #[derive(Clone)]
         enum RenderType<'a> {
             Before,
@@ -42,20 +171,46 @@ impl Display for Block {
             After,
         }
 
+        let glyphs = style.glyphs();
+        let vertical = glyphs.vertical;
+
+        // Everything besides the comment: the indent, the box and the figure inside it, and a space
+        let comment_width = style.width
+            .map(|width| width.saturating_sub(figure_width + 9).max(MIN_COMMENT_WIDTH));
+
+        let line = |[left, fill, right]: [char; 3]| {
+            format!("    {left}{}{right}\n", fill.to_string().repeat(figure_width + 2))
+        };
+
         let segments = self.0.iter().map(RenderType::Segment);
         let with_dividers = Itertools::intersperse(segments, RenderType::Divider);
         let with_bookends = once(RenderType::Before).chain(with_dividers).chain(once(RenderType::After));
 
         for render in with_bookends {
             match render {
-                RenderType::Before => { f.write_str("    ┌───────┐\n")?; },
+                RenderType::Before => f.write_str(&line(glyphs.top))?,
                 RenderType::Segment(segment) => {
-                    for Cell { figure, comment } in &segment.0 {
-                        f.write_fmt(format_args!("    │ {:<5} │ {}\n", figure, comment))?;
+                    for Cell { figure, comment, tone } in &segment.0 {
+                        let (figure_color, comment_color) = match style.color {
+                            true => tone.colors(),
+                            false => (None, None),
+                        };
+
+                        let mut lines = wrap(comment, comment_width).into_iter();
+                        let first = lines.next().unwrap_or_default();
+
+                        // Padded before colouring, as the escape codes take up no room on screen
+                        let figure = paint(&format!("{figure:<figure_width$}"), figure_color);
+
+                        f.write_fmt(format_args!("    {vertical} {figure} {vertical} {}\n", paint(&first, comment_color)))?;
+
+                        for rest in lines {
+                            f.write_fmt(format_args!("    {vertical} {:figure_width$} {vertical} {}\n", "", paint(&rest, comment_color)))?;
+                        }
                     }
                 },
-                RenderType::Divider => { f.write_str("    ├───────┤\n")?; },
-                RenderType::After => { f.write_str("    └───────┘\n")?; },
+                RenderType::Divider => f.write_str(&line(glyphs.divider))?,
+                RenderType::After => f.write_str(&line(glyphs.bottom))?,
             }
         }
 
@@ -63,16 +218,44 @@ impl Display for Block {
     }
 }
 
-impl Display for Column {
+impl Display for Styled<'_, Block> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Styled(block, style) = self;
+
+        block.render(f, style, block.figure_width())
+    }
+}
+
+/// Every block shares the width of the widest figure, so that they line up
+impl Display for Styled<'_, Column> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for block in &self.0 {
-            f.write_fmt(format_args!("{block}"))?;
+        let Styled(Column(blocks), style) = self;
+
+        let figure_width = blocks.iter()
+            .map(Block::figure_width)
+            .max()
+            .unwrap_or(MIN_FIGURE_WIDTH);
+
+        for block in blocks {
+            block.render(f, style, figure_width)?;
         }
 
         Ok(())
     }
 }
 
+impl Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Styled(self, Style::default()).fmt(f)
+    }
+}
+
+impl Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Styled(self, Style::default()).fmt(f)
+    }
+}
+
 #[test]
 fn check_display_feature() {
     // Must be run using
@@ -84,16 +267,19 @@ fn check_display_feature() {
                 Cell {
                     figure: "+15m".into(),
                     comment: "A comment".into(),
+                    tone: Tone::Neutral,
                 },
                 Cell {
                     figure: "-40m".into(),
                     comment: "Another comment".into(),
+                    tone: Tone::Neutral,
                 }
             ]),
             Segment(vec![
                 Cell {
                     figure: "+1:30".into(),
-                    comment: "Lots of time here".into()
+                    comment: "Lots of time here".into(),
+                    tone: Tone::Neutral,
                 }
             ])
         ]),
@@ -102,18 +288,21 @@ fn check_display_feature() {
                 Cell {
                     figure: "+3:00".into(),
                     comment: "By itself".into(),
+                    tone: Tone::Neutral,
                 }
             ]),
             Segment(vec![
                 Cell {
                     figure: "-6:00".into(),
                     comment: "Alone and yet together".into(),
+                    tone: Tone::Neutral,
                 }
             ]),
             Segment(vec![
                 Cell {
                     figure: "+9:00".into(),
                     comment: "Like two passing ships".into(),
+                    tone: Tone::Neutral,
                 }
             ]),
         ])
@@ -122,3 +311,44 @@ fn check_display_feature() {
     println!("{column}");
 }
 
+
+#[test]
+fn check_figures_and_comments_fit() {
+    let block = Block(vec![
+        Segment(vec![
+            Cell {
+                figure: "+100:00".into(),
+                comment: "Week starting 16th September 2024".into(),
+                tone: Tone::Neutral,
+            }
+        ]),
+    ]);
+
+    let style = Style { ascii: true, width: Some(36), color: false };
+
+    let expected = "    +---------+
+    | +100:00 | Week starting 16th
+    |         | September 2024
+    +---------+
+";
+
+    assert_eq!(Styled(&block, style).to_string(), expected);
+}
+
+
+#[test]
+fn check_headings_colour_their_figures() {
+    let block = Block(vec![
+        Segment(vec![
+            Cell {
+                figure: "-1:00".into(),
+                comment: "Week starting 16th September 2024".into(),
+                tone: Tone::DeficitHeading,
+            }
+        ]),
+    ]);
+
+    let style = Style { ascii: true, width: None, color: true };
+
+    assert!(Styled(&block, style).to_string().contains("| \x1b[1;31m-1:00\x1b[0m | \x1b[1mWeek starting 16th September 2024\x1b[0m\n"));
+}